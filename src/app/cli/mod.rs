@@ -0,0 +1,249 @@
+mod output;
+
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::PathBuf,
+};
+
+use anyhow::{bail, Context};
+use chrono::Utc;
+use norganisers_lib::{
+    backlinks, sync_wiki_links, Note, NoteBackend, NoteBackendError, UnsavedNote, WikiLink,
+};
+
+use super::{
+    config::AppConfig,
+    editor::edit_in_nvim,
+    note_store::NoteStore,
+    searching::{search_notes, Search},
+};
+use crate::args::{Command, OutputArgs, OutputFormat, TagCommand};
+use output::{write_note, write_notes, LIST_FIELDS, SHOW_FIELDS};
+
+// Runs `command` against `backend` without the terminal UI, writing what it prints to `out`.
+// `piped` is stdin, when text is piped to it instead of it being a terminal.
+pub fn run_command<B: NoteBackend>(
+    backend: &B,
+    config: &AppConfig,
+    command: Command,
+    piped: Option<&mut dyn Read>,
+    out: &mut impl Write,
+) -> anyhow::Result<()> {
+    match command {
+        Command::Add {
+            label,
+            label_option,
+            text,
+            file,
+            tags,
+        } => {
+            let label = label.or(label_option);
+            let text = read_text(text, file, piped, label.is_none())?;
+            let Some(note) = UnsavedNote::from_text(label, text, tags, Utc::now()) else {
+                bail!("The note needs a label, give one or a text that starts with a line to use");
+            };
+            let id = backend.add_note(note)?;
+            let notes = backend.retrieve_notes()?;
+            let mut added = find_note(&notes, id)?.clone();
+            let unresolved = sync_wiki_links(&mut added, "", &notes);
+            if !added.related_notes.is_empty() {
+                backend.update_note(&added)?;
+            }
+            report_unresolved(&unresolved);
+            writeln!(out, "{}", added.id)?;
+        }
+        Command::List { output } => {
+            let notes = backend.retrieve_notes()?;
+            let notes: Vec<&Note> = notes.iter().collect();
+            print_notes(out, &notes, &output)?;
+        }
+        Command::Show { id, output } => {
+            let notes = backend.retrieve_notes()?;
+            let note = find_note(&notes, id)?;
+            if let Some(format) = output_format(&output) {
+                let fields = output.fields_or(&SHOW_FIELDS);
+                return write_note(out, note, format, fields);
+            }
+            writeln!(out, "{}: {}", note.id, note.label)?;
+            writeln!(out, "created: {}", note.created_at.to_rfc3339())?;
+            if !note.tags.is_empty() {
+                writeln!(out, "tags: {}", note.tags.join(", "))?;
+            }
+            if !note.related_notes.is_empty() {
+                let links: Vec<String> = note.related_notes.iter().map(usize::to_string).collect();
+                writeln!(out, "links: {}", links.join(", "))?;
+            }
+            writeln!(out)?;
+            writeln!(out, "{}", note.text)?;
+        }
+        Command::Edit { id, label, text } => {
+            let notes = backend.retrieve_notes()?;
+            let note = find_note(&notes, id)?;
+            let mut edited = note.clone();
+            if label.is_none() && text.is_none() {
+                edited.text = edit_in_nvim(&note.text)?;
+            }
+            if let Some(label) = label {
+                edited.label = label;
+            }
+            if let Some(text) = text {
+                edited.text = text;
+            }
+            let unresolved = sync_wiki_links(&mut edited, &note.text, &notes);
+            if edited != *note {
+                backend.update_note(&edited)?;
+            }
+            report_unresolved(&unresolved);
+        }
+        Command::Rm { id, force } => {
+            let notes = backend.retrieve_notes()?;
+            let note = find_note(&notes, id)?;
+            let linked_from: Vec<String> = backlinks(&notes, id)
+                .iter()
+                .map(|other| format!("{}: {}", other.id, other.label))
+                .collect();
+            if !linked_from.is_empty() && !force {
+                bail!(
+                    "\"{}\" is linked from {}, give --force to delete it and these links",
+                    note.label,
+                    linked_from.join(", ")
+                );
+            }
+            backend.delete_note(note)?;
+        }
+        Command::Tag(TagCommand::Add { id, tags }) => {
+            update_note(backend, id, |note| {
+                for tag in tags {
+                    if !note.tags.contains(&tag) {
+                        note.tags.push(tag);
+                    }
+                }
+            })?;
+        }
+        Command::Tag(TagCommand::Remove { id, tags }) => {
+            update_note(backend, id, |note| {
+                note.tags.retain(|tag| !tags.contains(tag));
+            })?;
+        }
+        Command::Link { from, to } => {
+            if from == to {
+                bail!("A note can not link to itself");
+            }
+            find_note(&backend.retrieve_notes()?, to)?;
+            update_note(backend, from, |note| {
+                if !note.related_notes.contains(&to) {
+                    note.related_notes.push(to);
+                }
+            })?;
+        }
+        Command::Pick { .. } => bail!("Picking a note needs the terminal UI"),
+        Command::Search {
+            query,
+            tags,
+            output,
+        } => {
+            let note_store = NoteStore::new(backend.retrieve_notes()?);
+            let search = Search {
+                note_search: query.join(" "),
+                tag_search: tags,
+                ..Default::default()
+            };
+            let scores = search_notes(&note_store, &search, &config.search_backend);
+            let mut matched: Vec<&Note> = note_store
+                .get_notes_unfiltered()
+                .iter()
+                .filter(|note| scores.contains_key(&note.id))
+                .collect();
+            matched.sort_by(|a, b| {
+                scores[&b.id]
+                    .total_cmp(&scores[&a.id])
+                    .then(a.id.cmp(&b.id))
+            });
+            print_notes(out, &matched, &output)?;
+        }
+    }
+    Ok(())
+}
+
+// Prints the notes one per line like the note list, unless a format is asked for
+fn print_notes(out: &mut impl Write, notes: &[&Note], output: &OutputArgs) -> anyhow::Result<()> {
+    match output_format(output) {
+        Some(format) => write_notes(out, notes, format, output.fields_or(&LIST_FIELDS)),
+        None => {
+            for note in notes {
+                writeln!(out, "{}", note_line(note))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+// Picking fields only makes sense for formatted output, a table is the closest to the default
+fn output_format(output: &OutputArgs) -> Option<OutputFormat> {
+    output
+        .format
+        .or((!output.fields.is_empty()).then_some(OutputFormat::Table))
+}
+
+// The text of an added note, from `--text`, `--file` or else piped stdin. `--file -` also reads
+// stdin from the terminal. Piped stdin is only read unasked for a note without a label, so `add`
+// in a `while read` loop leaves the rest of the loop's input alone.
+fn read_text(
+    text: Option<String>,
+    file: Option<PathBuf>,
+    piped: Option<&mut dyn Read>,
+    no_label: bool,
+) -> anyhow::Result<String> {
+    let mut read = String::new();
+    match (text, file, piped) {
+        (Some(text), _, _) => return Ok(text),
+        (None, Some(file), Some(piped)) if file.as_os_str() == "-" => {
+            piped.read_to_string(&mut read)?;
+        }
+        (None, Some(file), None) if file.as_os_str() == "-" => {
+            io::stdin().read_to_string(&mut read)?;
+        }
+        (None, Some(file), _) => {
+            return fs::read_to_string(&file)
+                .with_context(|| format!("Could not read {}", file.display()));
+        }
+        (None, None, Some(piped)) if no_label => {
+            piped.read_to_string(&mut read)?;
+        }
+        (None, None, _) => {}
+    }
+    Ok(read)
+}
+
+fn find_note(notes: &[Note], id: usize) -> Result<&Note, NoteBackendError> {
+    notes
+        .iter()
+        .find(|note| note.id == id)
+        .ok_or(NoteBackendError::NotFound(id))
+}
+
+// Applies `change` to the stored note with `id` and saves it
+fn update_note<B: NoteBackend>(
+    backend: &B,
+    id: usize,
+    change: impl FnOnce(&mut Note),
+) -> anyhow::Result<()> {
+    let mut note = find_note(&backend.retrieve_notes()?, id)?.clone();
+    change(&mut note);
+    backend.update_note(&note)?;
+    Ok(())
+}
+
+// A note as shown in the note list, e.g. `3: Waterdeep [city] [sword coast]`
+fn note_line(note: &Note) -> String {
+    let tags: String = note.tags.iter().map(|tag| format!(" [{}]", tag)).collect();
+    format!("{}: {}{}", note.id, note.label, tags)
+}
+
+// Unresolved links do not stop a command, but are worth knowing about
+fn report_unresolved(unresolved: &[WikiLink]) {
+    for link in unresolved {
+        eprintln!("warning: {} does not match any note", link);
+    }
+}
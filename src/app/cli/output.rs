@@ -0,0 +1,195 @@
+use std::io::Write;
+
+use norganisers_lib::Note;
+use serde_json::{Map, Value};
+
+use crate::args::{NoteField, OutputArgs, OutputFormat};
+
+// Printed when `--fields` is not given. The text is left out of listings, it makes them hard to
+// read, `show` prints it too.
+pub const LIST_FIELDS: [NoteField; 5] = [
+    NoteField::Id,
+    NoteField::Label,
+    NoteField::Tags,
+    NoteField::CreatedAt,
+    NoteField::RelatedNotes,
+];
+pub const SHOW_FIELDS: [NoteField; 7] = [
+    NoteField::Id,
+    NoteField::Label,
+    NoteField::Text,
+    NoteField::Tags,
+    NoteField::CreatedAt,
+    NoteField::RelatedNotes,
+    NoteField::Revision,
+];
+
+impl OutputArgs {
+    pub fn fields_or<'a>(&'a self, default: &'a [NoteField]) -> &'a [NoteField] {
+        if self.fields.is_empty() {
+            default
+        } else {
+            &self.fields
+        }
+    }
+}
+
+impl NoteField {
+    pub fn name(self) -> &'static str {
+        match self {
+            NoteField::Id => "id",
+            NoteField::Label => "label",
+            NoteField::Text => "text",
+            NoteField::Tags => "tags",
+            NoteField::CreatedAt => "created_at",
+            NoteField::RelatedNotes => "related_notes",
+            NoteField::Revision => "revision",
+        }
+    }
+    fn json(self, note: &Note) -> Value {
+        match self {
+            NoteField::Id => note.id.into(),
+            NoteField::Label => note.label.clone().into(),
+            NoteField::Text => note.text.clone().into(),
+            NoteField::Tags => note.tags.clone().into(),
+            NoteField::CreatedAt => note.created_at.to_rfc3339().into(),
+            NoteField::RelatedNotes => note.related_notes.clone().into(),
+            NoteField::Revision => note.revision.into(),
+        }
+    }
+    // Lists are joined with commas, the text is left as it is for the formats to escape
+    pub fn text(self, note: &Note) -> String {
+        match self {
+            NoteField::Id => note.id.to_string(),
+            NoteField::Label => note.label.clone(),
+            NoteField::Text => note.text.clone(),
+            NoteField::Tags => note.tags.join(","),
+            NoteField::CreatedAt => note.created_at.to_rfc3339(),
+            NoteField::RelatedNotes => note
+                .related_notes
+                .iter()
+                .map(usize::to_string)
+                .collect::<Vec<String>>()
+                .join(","),
+            NoteField::Revision => note.revision.to_string(),
+        }
+    }
+}
+
+pub fn write_notes(
+    out: &mut impl Write,
+    notes: &[&Note],
+    format: OutputFormat,
+    fields: &[NoteField],
+) -> anyhow::Result<()> {
+    match format {
+        OutputFormat::Json => {
+            let objects: Vec<Value> = notes.iter().map(|note| json_object(note, fields)).collect();
+            serde_json::to_writer_pretty(&mut *out, &objects)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Jsonl => {
+            for note in notes {
+                serde_json::to_writer(&mut *out, &json_object(note, fields))?;
+                writeln!(out)?;
+            }
+        }
+        OutputFormat::Tsv => {
+            let header: Vec<&str> = fields.iter().map(|field| field.name()).collect();
+            writeln!(out, "{}", header.join("\t"))?;
+            for note in notes {
+                let row: Vec<String> = fields
+                    .iter()
+                    .map(|field| escape_tsv(&field.text(note)))
+                    .collect();
+                writeln!(out, "{}", row.join("\t"))?;
+            }
+        }
+        OutputFormat::Table => {
+            let rows = text_rows(notes, fields, |text| text.replace(['\r', '\n'], " "));
+            let widths: Vec<usize> = (0..fields.len())
+                .map(|i| {
+                    rows.iter()
+                        .map(|row| row[i].chars().count())
+                        .max()
+                        .unwrap_or(0)
+                })
+                .collect();
+            for row in rows {
+                let cells: Vec<String> = row
+                    .iter()
+                    .zip(&widths)
+                    .map(|(cell, width)| format!("{:width$}", cell, width = width))
+                    .collect();
+                writeln!(out, "{}", cells.join("  ").trim_end())?;
+            }
+        }
+        OutputFormat::Markdown => {
+            let rows = text_rows(notes, fields, escape_markdown);
+            for (i, row) in rows.iter().enumerate() {
+                writeln!(out, "| {} |", row.join(" | "))?;
+                if i == 0 {
+                    writeln!(out, "|{}", "---|".repeat(fields.len()))?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Like `write_notes`, but json is written as a single object
+pub fn write_note(
+    out: &mut impl Write,
+    note: &Note,
+    format: OutputFormat,
+    fields: &[NoteField],
+) -> anyhow::Result<()> {
+    if format == OutputFormat::Json {
+        serde_json::to_writer_pretty(&mut *out, &json_object(note, fields))?;
+        writeln!(out)?;
+        Ok(())
+    } else {
+        write_notes(out, &[note], format, fields)
+    }
+}
+
+fn json_object(note: &Note, fields: &[NoteField]) -> Value {
+    let object: Map<String, Value> = fields
+        .iter()
+        .map(|field| (field.name().to_string(), field.json(note)))
+        .collect();
+    Value::Object(object)
+}
+
+// The header followed by a row for every note, with every cell passed through `escape`
+fn text_rows(
+    notes: &[&Note],
+    fields: &[NoteField],
+    escape: impl Fn(&str) -> String,
+) -> Vec<Vec<String>> {
+    let header = fields
+        .iter()
+        .map(|field| field.name().to_string())
+        .collect();
+    let rows = notes.iter().map(|note| {
+        fields
+            .iter()
+            .map(|field| escape(&field.text(note)))
+            .collect()
+    });
+    std::iter::once(header).chain(rows).collect()
+}
+
+// Tabs and newlines would break the rows apart, so they are written as escapes
+fn escape_tsv(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\r', "\\r")
+        .replace('\n', "\\n")
+}
+
+fn escape_markdown(text: &str) -> String {
+    text.replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
@@ -0,0 +1,51 @@
+use clap_complete::CompletionCandidate;
+use norganisers_lib::{JsonBackend, MarkdownDirBackend, Note, NoteBackend, SqliteBackend};
+
+use super::{
+    config::{AppConfig, NoteBackendType},
+    model_helpers::get_tag_set,
+};
+
+// Completes note ids from the configured backend, shells that can show it get the label too
+pub fn complete_note_ids() -> Vec<CompletionCandidate> {
+    note_id_candidates(&completion_notes())
+}
+
+// Completes the tags used by the notes of the configured backend
+pub fn complete_tags() -> Vec<CompletionCandidate> {
+    tag_candidates(&completion_notes())
+}
+
+pub fn note_id_candidates(notes: &[Note]) -> Vec<CompletionCandidate> {
+    let mut notes: Vec<&Note> = notes.iter().collect();
+    notes.sort_by_key(|note| note.id);
+    notes
+        .into_iter()
+        .map(|note| {
+            CompletionCandidate::new(note.id.to_string()).help(Some(note.label.clone().into()))
+        })
+        .collect()
+}
+
+pub fn tag_candidates(notes: &[Note]) -> Vec<CompletionCandidate> {
+    let mut tags = get_tag_set(notes);
+    tags.sort();
+    tags.into_iter().map(CompletionCandidate::new).collect()
+}
+
+// Completions are asked for while typing, so the data is only read: nothing is locked, created
+// or migrated, and every failure just means no candidates
+fn completion_notes() -> Vec<Note> {
+    let Ok(Some(config)) = AppConfig::load_existing() else {
+        return Vec::new();
+    };
+    let notes = match config.note_backend {
+        NoteBackendType::Json => JsonBackend::new(config.data_file_path).retrieve_notes_unlocked(),
+        NoteBackendType::Sqlite => SqliteBackend::open_read_only(config.data_file_path)
+            .and_then(|backend| backend.retrieve_notes()),
+        NoteBackendType::Markdown => {
+            MarkdownDirBackend::new(config.data_file_path).retrieve_notes()
+        }
+    };
+    notes.unwrap_or_default()
+}
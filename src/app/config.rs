@@ -2,20 +2,34 @@ use std::{
     env::{self},
     fs::{self, File},
     io::{BufWriter, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 use dirs::home_dir;
 use norganisers_lib::{NoteBlob, BACKEND_VERSION};
 use serde::{Deserialize, Deserializer, Serialize};
+use tempfile::NamedTempFile;
 
-use crate::APP_VERSION;
+use super::searching::SavedSearch;
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum NoteBackendType {
     #[default]
     Json,
+    Sqlite,
+    Markdown,
+}
+
+// How searches are performed, `fzf` needs the fzf executable to be installed and `fulltext`
+// matches whole words (or their start) ranked by relevance
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchBackendType {
+    #[default]
+    Native,
+    Fzf,
+    FullText,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -23,42 +37,24 @@ pub struct AppConfig {
     #[serde(deserialize_with = "deserialize_and_expand")]
     pub data_file_path: PathBuf,
     pub note_backend: NoteBackendType,
+    #[serde(default)]
+    pub search_backend: SearchBackendType,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub saved_searches: Vec<SavedSearch>,
 }
 
 impl AppConfig {
     pub fn load() -> anyhow::Result<AppConfig> {
-        let config_path = if let Ok(config_home) = env::var("XDG_CONFIG_HOME") {
-            let path: PathBuf = [
-                config_home,
-                "norganise-rs".to_string(),
-                "config.json".to_string(),
-            ]
-            .iter()
-            .collect();
-            path
-        } else if let Ok(home) = env::var("HOME") {
-            let path: PathBuf = [
-                home,
-                ".config".to_string(),
-                "norganise-rs".to_string(),
-                "config.json".to_string(),
-            ]
-            .iter()
-            .collect();
-            path
-        } else {
-            panic!("No user home directory found!")
-        };
+        let config_path = config_path();
         let config = if !config_path.exists() {
             let dir_path = config_path.parent().unwrap();
-            let data_path: PathBuf = vec![dir_path.to_str().unwrap(), "notes.json"]
-                .iter()
-                .collect();
+            let data_path: PathBuf = [dir_path.to_str().unwrap(), "notes.json"].iter().collect();
             // create config dir
             fs::create_dir(dir_path)?;
             // create notes blob file
             let note_blob = NoteBlob {
                 version: BACKEND_VERSION,
+                next_id: 1,
                 notes: Vec::default(),
             };
             let note_blob_file = File::create(&data_path)?;
@@ -68,6 +64,8 @@ impl AppConfig {
             let config = AppConfig {
                 data_file_path: data_path,
                 note_backend: NoteBackendType::Json,
+                search_backend: SearchBackendType::default(),
+                saved_searches: Vec::new(),
             };
             // create config file
             let config_file = File::create(&config_path)?;
@@ -82,6 +80,71 @@ impl AppConfig {
         };
         Ok(config)
     }
+    // Like `load`, but nothing is created when there is no config yet
+    pub fn load_existing() -> anyhow::Result<Option<AppConfig>> {
+        let config_path = config_path();
+        if !config_path.exists() {
+            return Ok(None);
+        }
+        let raw = fs::read_to_string(config_path)?;
+        Ok(Some(serde_json::from_str(&raw)?))
+    }
+    // Writes the config back, e.g. after a search was saved
+    pub fn save(&self) -> anyhow::Result<()> {
+        self.save_to(&config_path())
+    }
+    pub fn save_to(&self, path: &Path) -> anyhow::Result<()> {
+        let mut config = serde_json::to_value(self)?;
+        // A stored path starting with `~` is expanded when loading, keep it as it was written
+        let stored: Option<serde_json::Value> = fs::read_to_string(path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok());
+        if let Some(raw_path) = stored
+            .as_ref()
+            .and_then(|stored| stored["data_file_path"].as_str())
+        {
+            if expand_tilde(raw_path) == self.data_file_path {
+                config["data_file_path"] = raw_path.into();
+            }
+        }
+        // Written to a temporary file first, so a failed write leaves the old config intact
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let mut tmp_file = NamedTempFile::new_in(dir)?;
+        serde_json::to_writer_pretty(&mut tmp_file, &config)?;
+        tmp_file.as_file().sync_all()?;
+        tmp_file.persist(path)?;
+        Ok(())
+    }
+}
+
+// The search history is kept next to the config
+pub fn history_path() -> PathBuf {
+    config_path().with_file_name("search_history.json")
+}
+
+fn config_path() -> PathBuf {
+    if let Ok(config_home) = env::var("XDG_CONFIG_HOME") {
+        let path: PathBuf = [
+            config_home,
+            "norganise-rs".to_string(),
+            "config.json".to_string(),
+        ]
+        .iter()
+        .collect();
+        path
+    } else if let Ok(home) = env::var("HOME") {
+        let path: PathBuf = [
+            home,
+            ".config".to_string(),
+            "norganise-rs".to_string(),
+            "config.json".to_string(),
+        ]
+        .iter()
+        .collect();
+        path
+    } else {
+        panic!("No user home directory found!")
+    }
 }
 
 fn deserialize_and_expand<'de, D>(deserializer: D) -> Result<PathBuf, D::Error>
@@ -1,5 +1,5 @@
 mod nvim;
-pub use nvim::NvimEditor;
+pub use nvim::{edit_in_nvim, NvimEditor};
 
 pub trait TextEditor {
     fn open_temp_file(text: &str) -> anyhow::Result<String>;
@@ -15,17 +15,23 @@ impl TextEditor for NvimEditor {
         stdout().execute(LeaveAlternateScreen)?;
         disable_raw_mode()?;
 
-        let mut tmp_file = NamedTempFile::with_suffix(".md")?;
-        write!(tmp_file, "{}", text)?;
-        let path = tmp_file.path().to_owned();
-        let status = Command::new("nvim").arg(&path).status()?;
-        if !status.success() {
-            return Err(anyhow::anyhow!("Nvim exited with an error"));
-        }
-        let updated_text = fs::read_to_string(path)?;
+        let updated_text = edit_in_nvim(text)?;
 
         stdout().execute(EnterAlternateScreen)?;
         enable_raw_mode()?;
         Ok(updated_text)
     }
 }
+
+// Opens `text` in nvim in a temporary file and returns the saved text, without touching the
+// terminal mode. Used directly by the CLI, where there is no TUI to leave.
+pub fn edit_in_nvim(text: &str) -> anyhow::Result<String> {
+    let mut tmp_file = NamedTempFile::with_suffix(".md")?;
+    write!(tmp_file, "{}", text)?;
+    let path = tmp_file.path().to_owned();
+    let status = Command::new("nvim").arg(&path).status()?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("Nvim exited with an error"));
+    }
+    Ok(fs::read_to_string(path)?)
+}
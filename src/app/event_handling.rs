@@ -14,10 +14,21 @@ pub enum InputMode {
     Navigating,
     SelectionPopup,
     EditNoteInfoPopup,
+    ChoicePopup,
+    SaveSearchPopup,
 }
 
-pub fn handle_event<B: NoteBackend>(model: &Model<B>) -> anyhow::Result<Option<Message>> {
-    if event::poll(Duration::from_millis(250))? {
+pub fn handle_event<B: NoteBackend>(model: &mut Model<B>) -> anyhow::Result<Option<Message>> {
+    if let Some(results) = model.search_worker.try_recv() {
+        return Ok(Some(Message::SearchResults(results)));
+    }
+    // Check back soon for the results of a running search
+    let timeout = if model.search_worker.is_searching() {
+        Duration::from_millis(10)
+    } else {
+        Duration::from_millis(250)
+    };
+    if event::poll(timeout)? {
         if let Event::Key(key) = event::read()? {
             if key.kind == event::KeyEventKind::Press {
                 return Ok(handle_key(key, &model.input_mode));
@@ -34,6 +45,61 @@ fn handle_key(key: KeyEvent, input_mode: &InputMode) -> Option<Message> {
         InputMode::SearchPopup => handle_key_search_popup(key),
         InputMode::SelectionPopup => handle_key_selection_popup(key),
         InputMode::EditNoteInfoPopup => handle_note_popup(key),
+        InputMode::ChoicePopup => handle_key_choice_popup(key),
+        InputMode::SaveSearchPopup => handle_key_save_search_popup(key),
+    }
+}
+
+fn handle_key_choice_popup(key: KeyEvent) -> Option<Message> {
+    match key {
+        KeyEvent {
+            code: KeyCode::Char('j'),
+            ..
+        } => Some(Message::NextChoice),
+        KeyEvent {
+            code: KeyCode::Char('k'),
+            ..
+        } => Some(Message::PrevChoice),
+        KeyEvent {
+            code: KeyCode::Enter,
+            ..
+        } => Some(Message::SubmitChoice),
+        KeyEvent {
+            code: KeyCode::Char('d'),
+            ..
+        } => Some(Message::DeleteSavedSearch),
+        KeyEvent {
+            code: KeyCode::Esc, ..
+        } => Some(Message::CloseChoice),
+        _ => None,
+    }
+}
+
+fn handle_key_save_search_popup(key: KeyEvent) -> Option<Message> {
+    match key {
+        KeyEvent {
+            code: KeyCode::Char(c),
+            modifiers: KeyModifiers::NONE,
+            ..
+        } => Some(Message::AddChar(c)),
+        KeyEvent {
+            code: KeyCode::Char(c),
+            modifiers: KeyModifiers::SHIFT,
+            ..
+        } => Some(Message::AddChar(c)),
+        KeyEvent {
+            code: KeyCode::Backspace,
+            ..
+        } => Some(Message::RemoveChar),
+        KeyEvent {
+            code: KeyCode::Enter,
+            modifiers: KeyModifiers::NONE,
+            ..
+        } => Some(Message::SubmitForm),
+        KeyEvent {
+            code: KeyCode::Esc, ..
+        } => Some(Message::ClosePopup),
+        _ => None,
     }
 }
 
@@ -55,6 +121,10 @@ fn handle_key_selection_popup(key: KeyEvent) -> Option<Message> {
             code: KeyCode::Char('h'),
             ..
         } => Some(Message::UnmakeSelection),
+        KeyEvent {
+            code: KeyCode::Char('m'),
+            ..
+        } => Some(Message::NextTagMode),
         KeyEvent {
             code: KeyCode::Enter,
             ..
@@ -72,6 +142,33 @@ fn handle_key_search_popup(key: KeyEvent) -> Option<Message> {
             modifiers: KeyModifiers::CONTROL,
             ..
         } => Some(Message::OpenSelection),
+        KeyEvent {
+            code: KeyCode::Char('s'),
+            modifiers: KeyModifiers::CONTROL,
+            ..
+        } => Some(Message::OpenPopup(PopupType::SaveSearch)),
+        KeyEvent {
+            code: KeyCode::Char('p'),
+            modifiers: KeyModifiers::CONTROL,
+            ..
+        } => Some(Message::OlderSearch),
+        KeyEvent {
+            code: KeyCode::Char('n'),
+            modifiers: KeyModifiers::CONTROL,
+            ..
+        } => Some(Message::NewerSearch),
+        KeyEvent {
+            code: KeyCode::Char('r'),
+            modifiers: KeyModifiers::CONTROL,
+            ..
+        } => Some(Message::OpenSearchHistory),
+        KeyEvent {
+            code: KeyCode::Up, ..
+        } => Some(Message::OlderSearch),
+        KeyEvent {
+            code: KeyCode::Down,
+            ..
+        } => Some(Message::NewerSearch),
         KeyEvent {
             code: KeyCode::Char(c),
             modifiers: KeyModifiers::NONE,
@@ -97,6 +194,18 @@ fn handle_key_search_popup(key: KeyEvent) -> Option<Message> {
             code: KeyCode::F(1),
             ..
         } => Some(Message::OpenSelection),
+        KeyEvent {
+            code: KeyCode::F(2),
+            ..
+        } => Some(Message::NextSearchScope),
+        KeyEvent {
+            code: KeyCode::F(3),
+            ..
+        } => Some(Message::NextMatchMode),
+        KeyEvent {
+            code: KeyCode::F(4),
+            ..
+        } => Some(Message::NextTagMode),
         KeyEvent {
             code: KeyCode::Enter,
             modifiers: KeyModifiers::NONE,
@@ -188,6 +297,15 @@ fn handle_key_navigation(key: KeyEvent) -> Option<Message> {
             modifiers: KeyModifiers::NONE,
             ..
         } => Some(Message::NextSortMode),
+        KeyEvent {
+            code: KeyCode::Char('s'),
+            modifiers: KeyModifiers::CONTROL,
+            ..
+        } => Some(Message::OpenPopup(PopupType::SaveSearch)),
+        KeyEvent {
+            code: KeyCode::Char('f'),
+            ..
+        } => Some(Message::OpenSavedSearches),
         KeyEvent {
             code: KeyCode::Char('s'),
             modifiers: KeyModifiers::SHIFT,
@@ -28,7 +28,6 @@ impl Form {
         if byte_index == 0 {
             return;
         }
-        assert!(byte_index as i32 - 1 >= 0, "char out of bounds");
         self.fields[field_index].remove(byte_index - 1);
     }
     pub fn replace_field_content(&mut self, field_index: usize, content: &str) {
@@ -59,7 +58,4 @@ impl Form {
             Local::now().into(),
         )
     }
-    pub fn is_empty(&self) -> bool {
-        self.fields.iter().all(|s| s.is_empty())
-    }
 }
@@ -1,3 +1,5 @@
+mod cli;
+mod completion;
 mod config;
 mod editor;
 mod event_handling;
@@ -11,44 +13,109 @@ mod tests;
 mod view;
 mod view_components;
 
+pub use completion::{complete_note_ids, complete_tags};
 pub use config::AppConfig;
 
-use norganisers_lib::{JsonBackend, NoteBackend};
+use std::io::{stdin, stdout, IsTerminal, Read};
+
+use norganisers_lib::{JsonBackend, MarkdownDirBackend, Note, NoteBackend, SqliteBackend};
 use ratatui::{prelude::Backend, Terminal};
 
 use {
+    crate::args::{Command, NoteField},
     config::NoteBackendType,
     event_handling::handle_event,
     model::{update, Model, RunningState},
     view::view,
 };
 
-pub fn run_app(terminal: &mut Terminal<impl Backend>, config: AppConfig) -> anyhow::Result<()> {
+// Runs the terminal UI. With `pick`, Enter chooses a note instead of editing it, and the field of
+// the chosen note is returned.
+pub fn run_app(
+    terminal: &mut Terminal<impl Backend>,
+    config: AppConfig,
+    pick: Option<NoteField>,
+) -> anyhow::Result<Option<String>> {
+    let picking = pick.is_some();
+    let picked = match config.note_backend {
+        NoteBackendType::Json => {
+            let backend = JsonBackend::new(config.data_file_path.clone());
+            app_loop(terminal, config, backend, picking)
+        }
+        NoteBackendType::Sqlite => {
+            let backend = SqliteBackend::new(config.data_file_path.clone())?;
+            app_loop(terminal, config, backend, picking)
+        }
+        NoteBackendType::Markdown => {
+            let backend = MarkdownDirBackend::new(config.data_file_path.clone());
+            app_loop(terminal, config, backend, picking)
+        }
+    }?;
+    Ok(picked.zip(pick).map(|(note, field)| field.text(&note)))
+}
+
+// Runs a command line command against the configured backend, without the terminal UI
+pub fn run_cli(config: AppConfig, command: Command) -> anyhow::Result<()> {
+    let mut out = stdout().lock();
+    let mut stdin = stdin().lock();
+    let piped = (!stdin.is_terminal()).then_some(&mut stdin as &mut dyn Read);
     match config.note_backend {
         NoteBackendType::Json => {
             let backend = JsonBackend::new(config.data_file_path.clone());
-            app_loop(terminal, config, backend)
+            cli::run_command(&backend, &config, command, piped, &mut out)
+        }
+        NoteBackendType::Sqlite => {
+            let backend = SqliteBackend::new(config.data_file_path.clone())?;
+            cli::run_command(&backend, &config, command, piped, &mut out)
+        }
+        NoteBackendType::Markdown => {
+            let backend = MarkdownDirBackend::new(config.data_file_path.clone());
+            cli::run_command(&backend, &config, command, piped, &mut out)
         }
     }
 }
 
+// Migrates the json file to the current version. This runs before the terminal UI takes over the
+// screen, so the report printed to stderr can be read.
+pub fn migrate_data(config: &AppConfig) -> anyhow::Result<()> {
+    if config.note_backend != NoteBackendType::Json {
+        return Ok(());
+    }
+    let report = JsonBackend::new(config.data_file_path.clone()).migrate()?;
+    if !report.is_empty() {
+        eprintln!(
+            "Migrated {:?} from version {} to {}: {}",
+            config.data_file_path,
+            report.from_version,
+            report.to_version,
+            report.applied.join(", ")
+        );
+        if let Some(backup_path) = report.backup_path {
+            eprintln!("The previous version was backed up to {:?}", backup_path);
+        }
+    }
+    Ok(())
+}
+
 fn app_loop<B>(
     terminal: &mut Terminal<impl Backend>,
-    _config: AppConfig,
+    config: AppConfig,
     backend: B,
-) -> anyhow::Result<()>
+    picking: bool,
+) -> anyhow::Result<Option<Note>>
 where
     B: NoteBackend,
 {
-    let mut model = Model::new(backend)?;
+    let mut model = Model::new(backend, config)?;
+    model.picking = picking;
     while model.running_state != RunningState::Exit {
         terminal.draw(|f| view(&mut model, f))?;
 
-        let mut current_msg = handle_event(&model)?;
+        let mut current_msg = handle_event(&mut model)?;
 
         while current_msg.is_some() {
             current_msg = update(&mut model, terminal, current_msg.unwrap())
         }
     }
-    Ok(())
+    Ok(model.picked)
 }
@@ -1,14 +1,23 @@
 use super::{
+    config::{history_path, AppConfig},
     editor::{NvimEditor, TextEditor},
     event_handling::InputMode,
     forms::Form,
     model_helpers::*,
-    note_store::NoteStore,
-    searching::fzf_search,
-    view_components::{InteractiveList, Popup, PopupType, SelectionPopupFields, ViewComponents},
+    note_store::{NoteSortMode, NoteStore},
+    searching::{
+        SavedSearch, Search, SearchHistory, SearchResults, SearchWorker, TagFilter, TagTerm,
+    },
+    view_components::{
+        ChoicePopup, InteractiveList, Popup, PopupType, SelectionPopupFields, ViewComponents,
+    },
+};
+use chrono::Utc;
+use log::{error, info, warn};
+use norganisers_lib::{
+    backlinks, sync_wiki_links, JsonBackend, Note, NoteBackend, NoteBackendError, UnsavedNote,
+    WikiLink,
 };
-use log::{error, info};
-use norganisers_lib::{JsonBackend, NoteBackend};
 use ratatui::{prelude::Backend, Terminal};
 
 #[derive(Default, PartialEq, Eq)]
@@ -23,18 +32,68 @@ pub struct Model<N: NoteBackend = JsonBackend> {
     pub input_mode: InputMode,
     pub views: ViewComponents,
     pub note_store: NoteStore,
+    pub pending_choice: Option<PendingChoice>,
+    pub config: AppConfig,
+    // The last search, its scope and mode are kept when the search popup is opened again
+    pub search: Search,
+    pub history: SearchHistory,
+    pub search_worker: SearchWorker,
+    // Applied once the results of the running search are in, e.g. for saved searches
+    sort_mode_after_search: Option<NoteSortMode>,
+    // Started to pick a note, which is kept in `picked` instead of being edited
+    pub picking: bool,
+    pub picked: Option<Note>,
     backend: N,
 }
 
+// A question asked through the choice popup, waiting for an answer
+pub enum PendingChoice {
+    // `mine` could not be saved, since the stored note changed after it was loaded
+    ResolveConflict { mine: Note },
+    // `note` is linked from other notes, make sure it should be deleted
+    ConfirmDelete { note: Note },
+    // The note with `note_id` links to labels that no note has, offer to create them
+    CreateStubs { note_id: usize, labels: Vec<String> },
+    // The saved searches are listed, the chosen one is applied
+    ApplySavedSearch,
+    // Past searches matching the search popup, the chosen one is put back in the popup
+    RecallSearch { searches: Vec<Search> },
+}
+
+#[derive(Clone, Copy)]
+enum ConflictResolution {
+    KeepMine,
+    KeepTheirs,
+    OpenBoth,
+}
+
+const CONFLICT_RESOLUTIONS: [(ConflictResolution, &str); 3] = [
+    (ConflictResolution::KeepMine, "Keep mine"),
+    (ConflictResolution::KeepTheirs, "Keep theirs"),
+    (ConflictResolution::OpenBoth, "Open both in editor"),
+];
+
+const DELETE_CHOICES: [(bool, &str); 2] = [(true, "Delete"), (false, "Cancel")];
+
+const STUB_CHOICES: [(bool, &str); 2] = [(true, "Create stub notes"), (false, "Skip")];
+
 impl<B: NoteBackend> Model<B> {
-    pub fn new(note_backend: B) -> anyhow::Result<Self> {
-        let notes = note_backend.retrieve_notes().unwrap();
+    pub fn new(note_backend: B, config: AppConfig) -> anyhow::Result<Self> {
+        let notes = note_backend.retrieve_notes()?;
         Ok(Self {
             running_state: RunningState::default(),
             input_mode: InputMode::Navigating,
             backend: note_backend,
             views: ViewComponents::default(),
             note_store: NoteStore::new(notes),
+            pending_choice: None,
+            config,
+            search: Search::default(),
+            history: SearchHistory::load(history_path()),
+            search_worker: SearchWorker::spawn(),
+            sort_mode_after_search: None,
+            picking: false,
+            picked: None,
         })
     }
 }
@@ -60,31 +119,76 @@ pub fn update<B: NoteBackend>(
         Message::NextNote => model.views.note_list.next_selection(),
         Message::DeleteNote => {
             if let Some(selected) = model.views.note_list.selected_selection() {
-                if let Some(note) = model.note_store.get_note_as_mut(selected) {
-                    let res = model.backend.delete_note(note.id);
-                    let msg = handle_result(res);
-                    if msg.is_some() {
-                        return msg;
+                if let Some(note) = model.note_store.get_note(selected) {
+                    let linked_from: Vec<&str> =
+                        backlinks(model.note_store.get_notes_unfiltered(), note.id)
+                            .iter()
+                            .map(|other| other.label.as_str())
+                            .collect();
+                    if linked_from.is_empty() {
+                        return Some(Message::DeleteNoteConfirmed(note.clone()));
                     }
-                    return Some(Message::RetrieveNotes);
+                    let message = format!(
+                        "\"{}\" is linked from {}. Deleting it removes these links.",
+                        note.label,
+                        linked_from.join(", ")
+                    );
+                    let choices = DELETE_CHOICES.map(|(_, label)| label);
+                    model.views.choice_popup =
+                        Some(ChoicePopup::new("Delete note", message, &choices));
+                    model.pending_choice =
+                        Some(PendingChoice::ConfirmDelete { note: note.clone() });
+                    return Some(Message::InputMode(InputMode::ChoicePopup));
                 }
             };
         }
+        Message::DeleteNoteConfirmed(note) => {
+            let res = model.backend.delete_note(&note);
+            let msg = handle_result(res);
+            if msg.is_some() {
+                return msg;
+            }
+            return Some(Message::RetrieveNotes);
+        }
+        Message::EditNote if model.picking => {
+            if let Some(selected) = model.views.note_list.selected_selection() {
+                model.picked = model.note_store.get_note(selected).cloned();
+                model.running_state = RunningState::Exit;
+            }
+        }
         Message::EditNote => {
             if let Some(selected) = model.views.note_list.selected_selection() {
-                info!("Selected note index: {}", format!("{}", selected));
-                if let Some(note) = model.note_store.get_note_as_mut(selected) {
-                    info!("Selected note {}", format!("{:?}", note));
+                info!("Selected note index: {}", selected);
+                if let Some(note) = model.note_store.get_note(selected).cloned() {
+                    info!("Selected note {:?}", note);
                     if let Ok(text) = NvimEditor::open_temp_file(&note.text) {
-                        note.text = text;
-                        match model.backend.update_note(note) {
-                            Ok(()) => {}
-                            Err(e) => return Some(Message::Error(anyhow::anyhow!(e))),
+                        let mut edited = note.clone();
+                        edited.text = text;
+                        let unresolved = sync_wiki_links(
+                            &mut edited,
+                            &note.text,
+                            model.note_store.get_notes_unfiltered(),
+                        );
+                        // Saving an unchanged note would only make other instances' copies stale
+                        if edited == note {
+                            return Some(Message::ClearScreen);
+                        }
+                        let res = model.backend.update_note(&edited);
+                        if res.is_ok() {
+                            edited.revision += 1;
+                            model.note_store.update_note(edited.clone());
                         }
-                        match terminal.clear() {
-                            Ok(()) => return Some(Message::ClearScreen),
-                            Err(e) => return Some(Message::Error(anyhow::anyhow!(e))),
+                        if let Err(e) = terminal.clear() {
+                            return Some(Message::Error(anyhow::anyhow!(e)));
                         }
+                        return match res {
+                            Ok(()) if !unresolved.is_empty() => {
+                                Some(Message::UnresolvedLinks(edited.id, unresolved))
+                            }
+                            Ok(()) => Some(Message::ClearScreen),
+                            Err(NoteBackendError::Conflict(_)) => Some(Message::Conflict(edited)),
+                            res => handle_result(res),
+                        };
                     }
                 }
             };
@@ -96,9 +200,18 @@ pub fn update<B: NoteBackend>(
                     Message::InputMode(InputMode::NewNotePopup)
                 }
                 PopupType::SearchNote => {
+                    model.history.reset_position();
                     model.views.popup = Some(Popup::new(Form::new(2), popup_type));
                     Message::InputMode(InputMode::SearchPopup)
                 }
+                PopupType::SaveSearch => {
+                    // Only a search that is still applied can be saved
+                    if model.search.is_empty() || !model.note_store.is_filtered() {
+                        return None;
+                    }
+                    model.views.popup = Some(Popup::new(Form::new(1), popup_type));
+                    Message::InputMode(InputMode::SaveSearchPopup)
+                }
                 PopupType::EditNote => {
                     if let Some(selected) = model.views.note_list.selected_selection() {
                         if let Some(note) = &model.note_store.get_note(selected) {
@@ -122,6 +235,14 @@ pub fn update<B: NoteBackend>(
         Message::ClosePopup => {
             match model.input_mode {
                 InputMode::SelectionPopup => {}
+                // Searches are remembered however the popup is closed
+                InputMode::SearchPopup => {
+                    model.views.popup = None;
+                    model.history.push(&model.search);
+                    if let Err(e) = model.history.save() {
+                        return Some(Message::Error(e));
+                    }
+                }
                 _ => model.views.popup = None,
             }
             return Some(Message::InputMode(InputMode::Navigating));
@@ -130,9 +251,9 @@ pub fn update<B: NoteBackend>(
             let msg = if let Some(popup) = model.views.popup.as_mut() {
                 match popup.popup_type {
                     PopupType::NewNote => {
-                        if popup.state.form.field_content(0).len() > 0 {
+                        if !popup.state.form.field_content(0).is_empty() {
                             let note = popup.state.form.to_unsaved_note();
-                            let res = model.backend.add_note(note);
+                            let res = model.backend.add_note(note).map(|_| ());
                             let msg = handle_result(res);
                             if msg.is_some() {
                                 return msg;
@@ -142,22 +263,36 @@ pub fn update<B: NoteBackend>(
                             Ok(notes) => {
                                 model.note_store.update_notes(notes);
                             }
-                            Err(e) => panic!("{}", e),
+                            Err(e) => return Some(Message::Error(e.into())),
                         }
                         Some(Message::ClosePopup)
                     }
                     PopupType::SearchNote => Some(Message::ClosePopup),
+                    PopupType::SaveSearch => {
+                        let name = popup.state.form.field_content(0).trim().to_string();
+                        if !name.is_empty() {
+                            let saved = SavedSearch {
+                                name,
+                                search: model.search.clone(),
+                                sort_mode: model.note_store.current_sort_mode(),
+                            };
+                            // Saving under an existing name replaces that search
+                            let saved_searches = &mut model.config.saved_searches;
+                            match saved_searches.iter_mut().find(|s| s.name == saved.name) {
+                                Some(existing) => *existing = saved,
+                                None => saved_searches.push(saved),
+                            }
+                            if let Err(e) = model.config.save() {
+                                return Some(Message::Error(e));
+                            }
+                        }
+                        Some(Message::ClosePopup)
+                    }
                     PopupType::EditNote => {
-                        if popup.state.form.field_content(0).len() > 0 {
+                        if !popup.state.form.field_content(0).is_empty() {
                             let label = popup.state.form.field_content(0);
                             let tags = popup.state.form.field_content(1);
-                            let selected = if let Some(selected) =
-                                model.views.note_list.selected_selection()
-                            {
-                                selected
-                            } else {
-                                return None;
-                            };
+                            let selected = model.views.note_list.selected_selection()?;
                             let mut note = if let Some(note) = model.note_store.get_note(selected) {
                                 note.clone()
                             } else {
@@ -170,6 +305,10 @@ pub fn update<B: NoteBackend>(
                                 .map(|s| s.to_string())
                                 .collect();
                             let res = model.backend.update_note(&note);
+                            if let Err(NoteBackendError::Conflict(_)) = res {
+                                model.views.popup = None;
+                                return Some(Message::Conflict(note));
+                            }
                             let msg = handle_result(res);
                             if msg.is_some() {
                                 return msg;
@@ -178,12 +317,11 @@ pub fn update<B: NoteBackend>(
                                 Ok(notes) => {
                                     model.note_store.update_notes(notes);
                                 }
-                                Err(e) => panic!("{}", e),
+                                Err(e) => return Some(Message::Error(e.into())),
                             }
                         }
                         Some(Message::ClosePopup)
                     }
-                    _ => None,
                 }
             } else {
                 None
@@ -195,48 +333,114 @@ pub fn update<B: NoteBackend>(
                 model.note_store.update_notes(notes);
                 model.views.note_list.reset_selection();
             }
-            Err(e) => panic!("{}", e),
+            Err(e) => return Some(Message::Error(e.into())),
         },
         Message::PerformSearch => {
-            if let Some(popup) = &mut model.views.popup {
-                let form = &mut popup.state.form;
-                if !form.is_empty() {
-                    match fzf_search(
-                        &model.note_store.get_notes_unfiltered(),
-                        &form.field_content(0),
-                        &form.field_content(1),
-                    ) {
-                        Ok(matched) => {
-                            model.note_store.update_filter(matched);
-                            model.views.note_list.reset_selection();
-                        }
-                        Err(e) => panic!("{}", e),
+            if let Some(popup) = &model.views.popup {
+                let form = &popup.state.form;
+                model.search.note_search = form.field_content(0).to_string();
+                model.search.tag_search = form.field_content(1).to_string();
+                run_search(model);
+            }
+        }
+        Message::SearchResults(results) => {
+            model
+                .note_store
+                .update_filter(results.scores, results.highlights);
+            if let Some(sort_mode) = model.sort_mode_after_search.take() {
+                model.note_store.set_sort_mode(sort_mode);
+            }
+            model.views.note_list.reset_selection();
+        }
+        Message::OlderSearch => {
+            if let Some(search) = model.history.older(&model.search).cloned() {
+                return recall_search(model, search);
+            }
+        }
+        Message::NewerSearch => {
+            if let Some(search) = model.history.newer() {
+                return recall_search(model, search);
+            }
+        }
+        Message::OpenSearchHistory => {
+            let pattern = model
+                .views
+                .popup
+                .as_ref()
+                .map(|popup| popup.state.form.field_content(0))
+                .unwrap_or_default();
+            let searches = model.history.matching(pattern);
+            let summaries: Vec<String> = searches.iter().map(Search::summary).collect();
+            let summaries: Vec<&str> = summaries.iter().map(String::as_str).collect();
+            let message = if searches.is_empty() {
+                "No earlier searches match the note field."
+            } else {
+                "Earlier searches matching the note field, best match first"
+            };
+            model.views.choice_popup = Some(ChoicePopup::new(
+                "Search history",
+                message.to_string(),
+                &summaries,
+            ));
+            model.pending_choice = Some(PendingChoice::RecallSearch { searches });
+            return Some(Message::InputMode(InputMode::ChoicePopup));
+        }
+        Message::OpenSavedSearches => {
+            let names: Vec<&str> = model
+                .config
+                .saved_searches
+                .iter()
+                .map(|saved| saved.name.as_str())
+                .collect();
+            let message = if names.is_empty() {
+                "No saved searches yet, save a search with <Ctrl-s>."
+            } else {
+                "<d> - delete the selected search"
+            };
+            model.views.choice_popup = Some(ChoicePopup::new(
+                "Saved searches",
+                message.to_string(),
+                &names,
+            ));
+            model.pending_choice = Some(PendingChoice::ApplySavedSearch);
+            return Some(Message::InputMode(InputMode::ChoicePopup));
+        }
+        Message::DeleteSavedSearch => {
+            if let Some(PendingChoice::ApplySavedSearch) = model.pending_choice {
+                let selected = model
+                    .views
+                    .choice_popup
+                    .as_ref()
+                    .and_then(|choice_popup| choice_popup.selected_selection())
+                    .filter(|&i| i < model.config.saved_searches.len());
+                if let Some(selected) = selected {
+                    model.config.saved_searches.remove(selected);
+                    if let Err(e) = model.config.save() {
+                        return Some(Message::Error(e));
                     }
-                } else {
-                    model.note_store.remove_filter();
+                    return Some(Message::OpenSavedSearches);
                 }
             }
         }
         Message::CleanState => {
             model.input_mode = InputMode::Navigating;
+            model.search_worker.cancel();
             model.note_store.remove_filter();
             model.views.note_list.reset_selection();
         }
         Message::AddChar(c) => {
             if let Some(popup) = model.views.popup.as_mut() {
                 popup.add_char(c);
-                match popup.popup_type {
-                    PopupType::SearchNote => return Some(Message::PerformSearch),
-                    _ => {}
+                if let PopupType::SearchNote = popup.popup_type {
+                    return Some(Message::PerformSearch);
                 }
             }
         }
         Message::RemoveChar => {
             if let Some(popup) = model.views.popup.as_mut() {
                 popup.remove_char();
-                match popup.popup_type {
-                    PopupType::SearchNote => return Some(Message::PerformSearch),
-                    _ => {}
+                if let PopupType::SearchNote = popup.popup_type {
+                    return Some(Message::PerformSearch);
                 }
             }
         }
@@ -269,32 +473,49 @@ pub fn update<B: NoteBackend>(
         Message::UnmakeSelection => {
             if let Some(popup) = model.views.popup.as_mut() {
                 popup.unmake_selection();
-                let content = popup.retrieve_selection().join(",");
+                let content = selection_content(popup);
                 popup.replace_selected_field(&content);
-                match popup.popup_type {
-                    PopupType::SearchNote => return Some(Message::PerformSearch),
-                    _ => {}
+                if let PopupType::SearchNote = popup.popup_type {
+                    return Some(Message::PerformSearch);
                 }
             }
         }
         Message::MakeSelection => {
             if let Some(popup) = model.views.popup.as_mut() {
                 popup.make_selection();
-                let content = popup.retrieve_selection().join(",");
+                let content = selection_content(popup);
                 popup.replace_selected_field(&content);
-                match popup.popup_type {
-                    PopupType::SearchNote => return Some(Message::PerformSearch),
-                    _ => {}
+                if let PopupType::SearchNote = popup.popup_type {
+                    return Some(Message::PerformSearch);
                 }
             }
         }
         Message::CloseSelection => {
             if let Some(popup) = model.views.popup.as_mut() {
-                let content = popup.retrieve_selection().join(",");
+                let content = selection_content(popup);
                 popup.replace_selected_field(&content);
                 popup.close_selector();
+                return Some(Message::InputMode(popup_input_mode(&popup.popup_type)));
+            }
+            return Some(Message::InputMode(InputMode::Navigating));
+        }
+        Message::NextSearchScope => {
+            model.search.scope = model.search.scope.next();
+            return Some(Message::PerformSearch);
+        }
+        Message::NextMatchMode => {
+            model.search.mode = model.search.mode.next();
+            return Some(Message::PerformSearch);
+        }
+        Message::NextTagMode => {
+            if let Some(popup) = model.views.popup.as_mut() {
+                if let PopupType::SearchNote = popup.popup_type {
+                    let mut filter = TagFilter::parse(popup.state.form.field_content(1));
+                    filter.mode = filter.mode.next();
+                    popup.replace_field(1, &filter.to_string());
+                    return Some(Message::PerformSearch);
+                }
             }
-            return Some(Message::InputMode(InputMode::NewNotePopup));
         }
         Message::NextSortMode => {
             model.note_store.next_sort_mode();
@@ -302,12 +523,270 @@ pub fn update<B: NoteBackend>(
         Message::PrevSortMode => {
             model.note_store.prev_sort_mode();
         }
+        Message::Conflict(mine) => {
+            let message = format!(
+                "\"{}\" was changed since it was loaded. Which version should be kept?",
+                mine.label
+            );
+            let choices = CONFLICT_RESOLUTIONS.map(|(_, label)| label);
+            model.views.choice_popup = Some(ChoicePopup::new("Conflict", message, &choices));
+            model.pending_choice = Some(PendingChoice::ResolveConflict { mine });
+            return Some(Message::InputMode(InputMode::ChoicePopup));
+        }
+        Message::UnresolvedLinks(note_id, links) => {
+            let links_text: Vec<String> = links.iter().map(WikiLink::to_string).collect();
+            let labels: Vec<String> = links
+                .into_iter()
+                .filter_map(|link| match link {
+                    WikiLink::Label(label) => Some(label),
+                    WikiLink::Id(_) => None,
+                })
+                .collect();
+            warn!(
+                "Unresolved links in note {}: {}",
+                note_id,
+                links_text.join(", ")
+            );
+            // Notes can not be created with a chosen id, so only labels get stubs
+            let (message, choices) = if labels.is_empty() {
+                (
+                    format!("No notes found for {}.", links_text.join(", ")),
+                    vec!["Ok"],
+                )
+            } else {
+                (
+                    format!(
+                        "No notes found for {}. Create empty notes for the labels?",
+                        links_text.join(", ")
+                    ),
+                    STUB_CHOICES.map(|(_, label)| label).to_vec(),
+                )
+            };
+            model.views.choice_popup =
+                Some(ChoicePopup::new("Unresolved links", message, &choices));
+            if !labels.is_empty() {
+                model.pending_choice = Some(PendingChoice::CreateStubs { note_id, labels });
+            }
+            return Some(Message::InputMode(InputMode::ChoicePopup));
+        }
+        Message::NextChoice => {
+            if let Some(choice_popup) = model.views.choice_popup.as_mut() {
+                choice_popup.next_selection();
+            }
+        }
+        Message::PrevChoice => {
+            if let Some(choice_popup) = model.views.choice_popup.as_mut() {
+                choice_popup.prev_selection();
+            }
+        }
+        Message::SubmitChoice => {
+            let choice = model
+                .views
+                .choice_popup
+                .take()
+                .and_then(|choice_popup| choice_popup.selected_selection());
+            let pending_choice = model.pending_choice.take();
+            model.input_mode = InputMode::Navigating;
+            match (pending_choice, choice) {
+                (Some(PendingChoice::ResolveConflict { mine }), Some(choice)) => {
+                    let (resolution, _) = CONFLICT_RESOLUTIONS[choice];
+                    return resolve_conflict(model, terminal, mine, resolution);
+                }
+                (Some(PendingChoice::ResolveConflict { .. }), None) => {
+                    return Some(Message::RetrieveNotes)
+                }
+                (Some(PendingChoice::ConfirmDelete { note }), Some(choice)) => {
+                    let (delete, _) = DELETE_CHOICES[choice];
+                    if delete {
+                        return Some(Message::DeleteNoteConfirmed(note));
+                    }
+                }
+                (Some(PendingChoice::ConfirmDelete { .. }), None) => {}
+                (Some(PendingChoice::CreateStubs { note_id, labels }), Some(choice)) => {
+                    let (create, _) = STUB_CHOICES[choice];
+                    if create {
+                        return create_stub_notes(model, note_id, labels);
+                    }
+                }
+                (Some(PendingChoice::CreateStubs { .. }), None) => {}
+                (Some(PendingChoice::ApplySavedSearch), Some(choice)) => {
+                    if let Some(saved) = model.config.saved_searches.get(choice).cloned() {
+                        model.search = saved.search;
+                        model.sort_mode_after_search = Some(saved.sort_mode);
+                        run_search(model);
+                    }
+                }
+                (Some(PendingChoice::ApplySavedSearch), None) => {}
+                (Some(PendingChoice::RecallSearch { mut searches }), Some(choice)) => {
+                    model.input_mode = InputMode::SearchPopup;
+                    if choice < searches.len() {
+                        return recall_search(model, searches.swap_remove(choice));
+                    }
+                }
+                (Some(PendingChoice::RecallSearch { .. }), None) => {
+                    model.input_mode = InputMode::SearchPopup;
+                }
+                (None, _) => {}
+            }
+        }
+        Message::CloseChoice => {
+            model.views.choice_popup = None;
+            model.input_mode = InputMode::Navigating;
+            match model.pending_choice.take() {
+                // Show the stored version again, mine is dropped
+                Some(PendingChoice::ResolveConflict { .. }) => return Some(Message::RetrieveNotes),
+                Some(PendingChoice::ConfirmDelete { .. })
+                | Some(PendingChoice::CreateStubs { .. })
+                | Some(PendingChoice::ApplySavedSearch)
+                | None => {}
+                // Back to the search popup the history was opened from
+                Some(PendingChoice::RecallSearch { .. }) => {
+                    model.input_mode = InputMode::SearchPopup;
+                }
+            }
+        }
         Message::Error(e) => error!("{}", e),
     }
     None
 }
 
+fn resolve_conflict<B: NoteBackend>(
+    model: &mut Model<B>,
+    terminal: &mut Terminal<impl Backend>,
+    mut mine: Note,
+    resolution: ConflictResolution,
+) -> Option<Message> {
+    let theirs = match model.backend.retrieve_notes() {
+        Ok(notes) => notes.into_iter().find(|note| note.id == mine.id),
+        Err(e) => return Some(Message::Error(e.into())),
+    };
+    let Some(theirs) = theirs else {
+        return handle_result(Err(NoteBackendError::NotFound(mine.id)));
+    };
+    match resolution {
+        ConflictResolution::KeepMine => {}
+        ConflictResolution::KeepTheirs => return Some(Message::RetrieveNotes),
+        ConflictResolution::OpenBoth => {
+            let both = format!(
+                "<<<<<<< mine\n{}\n=======\n{}\n>>>>>>> theirs\n",
+                mine.text, theirs.text
+            );
+            let res = NvimEditor::open_temp_file(&both);
+            if let Err(e) = terminal.clear() {
+                return Some(Message::Error(anyhow::anyhow!(e)));
+            }
+            match res {
+                Ok(text) => mine.text = text,
+                Err(e) => return Some(Message::Error(e)),
+            }
+        }
+    }
+    // Write on top of their revision
+    mine.revision = theirs.revision;
+    match model.backend.update_note(&mine) {
+        Ok(()) => Some(Message::RetrieveNotes),
+        // Changed yet again while the user was choosing
+        Err(NoteBackendError::Conflict(_)) => Some(Message::Conflict(mine)),
+        res => handle_result(res),
+    }
+}
+
+// Puts `search` in the search popup and applies it
+fn recall_search<B: NoteBackend>(model: &mut Model<B>, search: Search) -> Option<Message> {
+    let popup = model.views.popup.as_mut()?;
+    popup.replace_field(0, &search.note_search);
+    popup.replace_field(1, &search.tag_search);
+    model.search = search;
+    Some(Message::PerformSearch)
+}
+
+// Filters the notes with `model.search` in the background, `Message::SearchResults` brings the
+// matches back. An empty search shows every note again.
+fn run_search<B: NoteBackend>(model: &mut Model<B>) {
+    if model.search.is_empty() {
+        model.search_worker.cancel();
+        model.note_store.remove_filter();
+        return;
+    }
+    model.search_worker.search(
+        &mut model.note_store,
+        model.search.clone(),
+        model.config.search_backend.clone(),
+    );
+}
+
+// The input mode of a popup, to return to once the selection popup opened from it is closed
+fn popup_input_mode(popup_type: &PopupType) -> InputMode {
+    match popup_type {
+        PopupType::NewNote => InputMode::NewNotePopup,
+        PopupType::SearchNote => InputMode::SearchPopup,
+        PopupType::SaveSearch => InputMode::SaveSearchPopup,
+        PopupType::EditNote => InputMode::EditNoteInfoPopup,
+    }
+}
+
+// The field content for the tags picked in the selection popup. The tag field of the search
+// popup keeps its mode and fuzzy terms, the picked tags replace the exact terms.
+fn selection_content(popup: &Popup) -> String {
+    let selected = popup.retrieve_selection();
+    if let PopupType::SearchNote = popup.popup_type {
+        let field = popup.state.selected_field;
+        let mut filter = TagFilter::parse(popup.state.form.field_content(field));
+        filter.terms.retain(|term| term.fuzzy);
+        filter
+            .terms
+            .extend(selected.iter().map(|tag| TagTerm::exact(tag)));
+        filter.to_string()
+    } else {
+        selected.join(",")
+    }
+}
+
+// Adds an empty note for every label, then links the note with `note_id` to them
+fn create_stub_notes<B: NoteBackend>(
+    model: &mut Model<B>,
+    note_id: usize,
+    labels: Vec<String>,
+) -> Option<Message> {
+    for label in labels {
+        let stub = UnsavedNote::new(label, String::new(), Vec::new(), Vec::new(), Utc::now());
+        if let Err(e) = model.backend.add_note(stub) {
+            return Some(Message::Error(e.into()));
+        }
+    }
+    let notes = match model.backend.retrieve_notes() {
+        Ok(notes) => notes,
+        Err(e) => return Some(Message::Error(e.into())),
+    };
+    let Some(mut note) = notes.iter().find(|note| note.id == note_id).cloned() else {
+        return handle_result(Err(NoteBackendError::NotFound(note_id)));
+    };
+    let text = note.text.clone();
+    let related_notes = note.related_notes.clone();
+    sync_wiki_links(&mut note, &text, &notes);
+    if note.related_notes != related_notes {
+        if let Some(msg) = handle_result(model.backend.update_note(&note)) {
+            return Some(msg);
+        }
+    }
+    Some(Message::RetrieveNotes)
+}
+
 pub enum Message {
+    Conflict(Note),
+    UnresolvedLinks(usize, Vec<WikiLink>),
+    NextChoice,
+    PrevChoice,
+    SubmitChoice,
+    CloseChoice,
+    NextSearchScope,
+    NextMatchMode,
+    NextTagMode,
+    OpenSavedSearches,
+    OlderSearch,
+    NewerSearch,
+    OpenSearchHistory,
+    DeleteSavedSearch,
     NextSortMode,
     PrevSortMode,
     UnmakeSelection,
@@ -318,8 +797,10 @@ pub enum Message {
     OpenSelection,
     CleanState,
     PerformSearch,
+    SearchResults(SearchResults),
     ClearScreen,
     DeleteNote,
+    DeleteNoteConfirmed(Note),
     RetrieveNotes,
     Error(anyhow::Error),
     SubmitForm,
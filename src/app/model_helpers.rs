@@ -1,18 +1,28 @@
 use std::collections::HashSet;
 
-use norganisers_lib::Note;
+use log::warn;
+use norganisers_lib::{Note, NoteBackendError};
 
 use super::model::Message;
 
-// Handle a Result that contains a unit `()` value
-pub fn handle_result(res: anyhow::Result<()>) -> Option<Message> {
+// Handle a backend Result that contains a unit `()` value
+pub fn handle_result(res: Result<(), NoteBackendError>) -> Option<Message> {
     match res {
         Ok(_) => None,
-        Err(e) => Some(Message::Error(e)),
+        // The note was removed outside of the application, show what is stored instead
+        Err(NoteBackendError::NotFound(id)) => {
+            warn!("Note with id {} no longer exists, reloading notes", id);
+            Some(Message::RetrieveNotes)
+        }
+        Err(NoteBackendError::Conflict(id)) => {
+            warn!("Note with id {} was changed elsewhere, reloading notes", id);
+            Some(Message::RetrieveNotes)
+        }
+        Err(e) => Some(Message::Error(e.into())),
     }
 }
 
-pub fn get_tag_set(notes: &Vec<Note>) -> Vec<String> {
+pub fn get_tag_set(notes: &[Note]) -> Vec<String> {
     let unique_tags: HashSet<String> = notes
         .iter()
         .flat_map(|note| note.tags.iter().cloned())
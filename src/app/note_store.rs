@@ -1,55 +1,150 @@
-use std::collections::HashSet;
+use std::{
+    cell::OnceCell,
+    collections::{HashMap, HashSet},
+};
 
-use log::info;
-use norganisers_lib::Note;
+use norganisers_lib::{Note, SearchIndex};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    model_helpers::get_tag_set,
+    searching::{Highlights, SearchScores},
+};
 
 pub struct NoteStore {
     // Notes
     notes: Vec<Note>,
-    matched_note_indices: Option<HashSet<usize>>,
+    // Index into `notes` -> relevance of the match
+    matched_note_indices: Option<HashMap<usize, f64>>,
+    // Note id -> what matched the search
+    highlights: HashMap<usize, Highlights>,
     // Tags
     tags: Vec<String>,
     sort_mode: NoteSortMode,
+    // The sort mode to go back to once the search is cleared, searches sort by relevance
+    sort_mode_before_search: Option<NoteSortMode>,
+    // Full-text index over `notes`, built by the first ranked search and kept up to date after
+    index: OnceCell<SearchIndex>,
+    // Ids of the notes added, changed or removed since the last `take_changes`
+    changed_ids: HashSet<usize>,
 }
 
 impl NoteStore {
     pub fn new(notes: Vec<Note>) -> Self {
-        let unique_tags: HashSet<String> = notes
-            .iter()
-            .flat_map(|note| note.tags.iter().cloned())
-            .collect();
-        let tags = unique_tags.into_iter().collect();
+        let tags = get_tag_set(&notes);
+        let changed_ids = notes.iter().map(|note| note.id).collect();
         Self {
             notes,
             matched_note_indices: None,
+            highlights: HashMap::new(),
             tags,
             sort_mode: NoteSortMode::None,
+            sort_mode_before_search: None,
+            index: OnceCell::new(),
+            changed_ids,
         }
     }
-    pub fn update_filter(&mut self, set: HashSet<usize>) {
+    pub fn update_filter(&mut self, scores: SearchScores, highlights: HashMap<usize, Highlights>) {
+        self.highlights = highlights;
+        if self.matched_note_indices.is_none() {
+            self.sort_mode_before_search = Some(self.sort_mode);
+            self.sort_mode = NoteSortMode::Relevance;
+        }
         self.matched_note_indices = Some(
             self.notes
                 .iter()
                 .enumerate()
-                .filter_map(|(i, note)| {
-                    if set.contains(&note.id) {
-                        Some(i)
-                    } else {
-                        None
-                    }
-                })
+                .filter_map(|(i, note)| Some((i, *scores.get(&note.id)?)))
                 .collect(),
         );
     }
     pub fn update_notes(&mut self, notes: Vec<Note>) {
-        let unique_tags: HashSet<String> = notes
+        self.remove_filter();
+        self.tags = get_tag_set(&notes);
+        // Only the notes that were added, changed or removed are reindexed
+        let old_notes: HashMap<usize, &Note> =
+            self.notes.iter().map(|note| (note.id, note)).collect();
+        let new_ids: HashSet<usize> = notes.iter().map(|note| note.id).collect();
+        let changed = notes
             .iter()
-            .flat_map(|note| note.tags.iter().cloned())
+            .filter(|note| old_notes.get(&note.id) != Some(note));
+        let removed: Vec<usize> = old_notes
+            .keys()
+            .filter(|id| !new_ids.contains(id))
+            .copied()
             .collect();
-        self.matched_note_indices = None;
-        self.tags = unique_tags.into_iter().collect();
+        for note in changed {
+            if let Some(index) = self.index.get_mut() {
+                index.insert(note);
+            }
+            self.changed_ids.insert(note.id);
+        }
+        for id in removed {
+            if let Some(index) = self.index.get_mut() {
+                index.remove(id);
+            }
+            self.changed_ids.insert(id);
+        }
         self.notes = notes;
     }
+    // Replaces the stored note with the same id as `note`
+    pub fn update_note(&mut self, note: Note) {
+        if let Some(stored) = self.notes.iter_mut().find(|stored| stored.id == note.id) {
+            if let Some(index) = self.index.get_mut() {
+                index.insert(&note);
+            }
+            self.changed_ids.insert(note.id);
+            // The highlighted positions are for the old version of the note
+            self.highlights.remove(&note.id);
+            *stored = note;
+        }
+    }
+    // Returns the notes added or changed and the ids of the notes removed since the last call
+    pub fn take_changes(&mut self) -> (Vec<Note>, Vec<usize>) {
+        let changed: Vec<Note> = self
+            .notes
+            .iter()
+            .filter(|note| self.changed_ids.contains(&note.id))
+            .cloned()
+            .collect();
+        for note in &changed {
+            self.changed_ids.remove(&note.id);
+        }
+        (changed, self.changed_ids.drain().collect())
+    }
+    // Applies changes taken from another store with `take_changes`
+    pub fn apply_changes(&mut self, changed: Vec<Note>, removed: Vec<usize>) {
+        let removed: HashSet<usize> = removed.into_iter().collect();
+        self.notes.retain(|note| !removed.contains(&note.id));
+        let positions: HashMap<usize, usize> = self
+            .notes
+            .iter()
+            .enumerate()
+            .map(|(i, note)| (note.id, i))
+            .collect();
+        if let Some(index) = self.index.get_mut() {
+            for id in &removed {
+                index.remove(*id);
+            }
+            for note in &changed {
+                index.insert(note);
+            }
+        }
+        for note in changed {
+            match positions.get(&note.id) {
+                Some(&i) => self.notes[i] = note,
+                None => self.notes.push(note),
+            }
+        }
+        self.tags = get_tag_set(&self.notes);
+    }
+    // Returns the ids of the notes matching every term of `query` with their relevance, best
+    // match first
+    pub fn ranked_search(&self, query: &str) -> Vec<(usize, f64)> {
+        self.index
+            .get_or_init(|| SearchIndex::new(&self.notes))
+            .search(query)
+    }
     // Applies filtering(if needed) and return notes
     pub fn get_notes(&self) -> Vec<&Note> {
         let notes: Vec<&Note> = self
@@ -63,13 +158,24 @@ impl NoteStore {
     // the selected sorting mode. If no search has been performed, all indices are returned.
     fn get_matched_note_indices_sorted(&self) -> Vec<usize> {
         let indices: Vec<usize> = if let Some(matched) = &self.matched_note_indices {
-            matched.iter().cloned().collect()
+            let mut indices: Vec<usize> = matched.keys().cloned().collect();
+            // Notes that sort equally stay in file order
+            indices.sort_unstable();
+            indices
         } else {
             (0..self.notes.len()).collect()
         };
 
+        let score = |i: usize| {
+            self.matched_note_indices
+                .as_ref()
+                .and_then(|matched| matched.get(&i))
+                .copied()
+                .unwrap_or(0.0)
+        };
         let mut sorted = indices;
         sorted.sort_by(|&a, &b| match self.sort_mode {
+            NoteSortMode::Relevance => score(b).total_cmp(&score(a)),
             NoteSortMode::AscCreated => self.notes[a].created_at.cmp(&self.notes[b].created_at),
             NoteSortMode::DesCreated => self.notes[b].created_at.cmp(&self.notes[a].created_at),
             NoteSortMode::LabelAsc => self.notes[a].label.cmp(&self.notes[b].label),
@@ -83,17 +189,7 @@ impl NoteStore {
         &self.notes
     }
     pub fn get_note(&self, index: usize) -> Option<&Note> {
-        self.get_notes().get(index).map(|note| *note)
-    }
-    pub fn get_note_as_mut(&mut self, index: usize) -> Option<&mut Note> {
-        // The provided index argument is the index as seen in the UI list
-        // indices is a sparse list of indices (filtered) of the notes that should be rendered
-        let indices = &self.get_matched_note_indices_sorted();
-        if let Some(note_index) = indices.get(index) {
-            self.notes.get_mut(*note_index)
-        } else {
-            None
-        }
+        self.get_notes().get(index).copied()
     }
     pub fn get_tags(&self) -> Vec<String> {
         let notes = self.get_notes();
@@ -105,41 +201,65 @@ impl NoteStore {
     }
     pub fn remove_filter(&mut self) {
         self.matched_note_indices = None;
+        self.highlights.clear();
+        if let Some(sort_mode) = self.sort_mode_before_search.take() {
+            self.sort_mode = sort_mode;
+        }
     }
     pub fn is_filtered(&self) -> bool {
         self.matched_note_indices.is_some()
     }
+    // Relevance is only offered while a search is active
     pub fn next_sort_mode(&mut self) {
         self.sort_mode = self.sort_mode.next();
+        if self.sort_mode == NoteSortMode::Relevance && !self.is_filtered() {
+            self.sort_mode = self.sort_mode.next();
+        }
     }
     pub fn prev_sort_mode(&mut self) {
         self.sort_mode = self.sort_mode.prev();
+        if self.sort_mode == NoteSortMode::Relevance && !self.is_filtered() {
+            self.sort_mode = self.sort_mode.prev();
+        }
+    }
+    // Relevance only applies to searches, without one the current sort mode is kept
+    pub fn set_sort_mode(&mut self, sort_mode: NoteSortMode) {
+        if sort_mode != NoteSortMode::Relevance || self.is_filtered() {
+            self.sort_mode = sort_mode;
+        }
     }
     pub fn current_sort_mode(&self) -> NoteSortMode {
         self.sort_mode
     }
-    pub fn get_current_matches(&self) -> &Option<HashSet<usize>> {
+    pub fn get_highlights(&self, id: usize) -> Option<&Highlights> {
+        self.highlights.get(&id)
+    }
+    pub fn get_current_matches(&self) -> &Option<HashMap<usize, f64>> {
         &self.matched_note_indices
     }
 }
 
-#[derive(PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum NoteSortMode {
     None,
     AscCreated,
     DesCreated,
     LabelAsc,
     LabelDesc,
+    // Best search match first
+    Relevance,
 }
 
 impl NoteSortMode {
     const fn modes() -> &'static [NoteSortMode] {
-        const MODES: [NoteSortMode; 5] = [
+        const MODES: [NoteSortMode; 6] = [
             NoteSortMode::None,
             NoteSortMode::AscCreated,
             NoteSortMode::DesCreated,
             NoteSortMode::LabelAsc,
             NoteSortMode::LabelDesc,
+            NoteSortMode::Relevance,
         ];
         &MODES
     }
@@ -152,7 +272,7 @@ impl NoteSortMode {
     fn prev(self) -> NoteSortMode {
         let modes = Self::modes();
         let idx = modes.iter().position(|&m| m == self).unwrap();
-        let prev = (idx - 1) % modes.len();
+        let prev = (idx + modes.len() - 1) % modes.len();
         modes[prev]
     }
 }
@@ -0,0 +1,126 @@
+// Scores are modelled on fzf: every matched character scores, and matches at the start of a word
+// or right after the previous match score extra. Gaps between matches cost a little.
+const SCORE_MATCH: i64 = 16;
+const PENALTY_GAP_START: i64 = 3;
+const PENALTY_GAP_EXTENSION: i64 = 1;
+const BONUS_BOUNDARY: i64 = 8;
+const BONUS_CAMEL: i64 = 7;
+const BONUS_CONSECUTIVE: i64 = 4;
+const BONUS_FIRST_CHAR_MULTIPLIER: i64 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    // Char indices of the matched characters in the haystack, ascending
+    pub positions: Vec<usize>,
+}
+
+// Matches every whitespace separated term of `pattern` as a subsequence of `haystack`. Matching
+// ignores case unless `pattern` contains an uppercase character.
+pub fn fuzzy_match(pattern: &str, haystack: &str) -> Option<FuzzyMatch> {
+    let case_sensitive = pattern.chars().any(char::is_uppercase);
+    let chars: Vec<char> = haystack.chars().collect();
+    let mut score = 0;
+    let mut positions = Vec::new();
+    for term in pattern.split_whitespace() {
+        let term: Vec<char> = term.chars().collect();
+        let term_positions = match_term(&term, &chars, case_sensitive)?;
+        score += score_positions(&chars, &term_positions);
+        positions.extend(term_positions);
+    }
+    positions.sort_unstable();
+    positions.dedup();
+    Some(FuzzyMatch { score, positions })
+}
+
+fn chars_eq(a: char, b: char, case_sensitive: bool) -> bool {
+    if case_sensitive {
+        a == b
+    } else {
+        a == b || a.to_lowercase().eq(b.to_lowercase())
+    }
+}
+
+// Finds the first occurrence of `term` and then walks back from its end, which gives the
+// shortest match ending there. Within that window the characters are matched greedily.
+fn match_term(term: &[char], chars: &[char], case_sensitive: bool) -> Option<Vec<usize>> {
+    if term.is_empty() {
+        return Some(Vec::new());
+    }
+    let mut term_idx = 0;
+    let mut end = None;
+    for (i, &c) in chars.iter().enumerate() {
+        if chars_eq(c, term[term_idx], case_sensitive) {
+            term_idx += 1;
+            if term_idx == term.len() {
+                end = Some(i);
+                break;
+            }
+        }
+    }
+    let end = end?;
+
+    let mut term_idx = term.len();
+    let mut start = end;
+    for i in (0..=end).rev() {
+        if chars_eq(chars[i], term[term_idx - 1], case_sensitive) {
+            term_idx -= 1;
+            if term_idx == 0 {
+                start = i;
+                break;
+            }
+        }
+    }
+
+    let mut positions = Vec::with_capacity(term.len());
+    let mut term_idx = 0;
+    for (i, &c) in chars.iter().enumerate().take(end + 1).skip(start) {
+        if term_idx < term.len() && chars_eq(c, term[term_idx], case_sensitive) {
+            positions.push(i);
+            term_idx += 1;
+        }
+    }
+    Some(positions)
+}
+
+fn bonus_at(chars: &[char], i: usize) -> i64 {
+    let Some(prev) = i.checked_sub(1).map(|prev| chars[prev]) else {
+        return BONUS_BOUNDARY;
+    };
+    let c = chars[i];
+    if !prev.is_alphanumeric() && c.is_alphanumeric() {
+        BONUS_BOUNDARY
+    } else if (prev.is_lowercase() && c.is_uppercase()) || (!prev.is_numeric() && c.is_numeric()) {
+        BONUS_CAMEL
+    } else {
+        0
+    }
+}
+
+fn score_positions(chars: &[char], positions: &[usize]) -> i64 {
+    let mut score = 0;
+    let mut prev: Option<usize> = None;
+    let mut consecutive_bonus = 0;
+    for (n, &i) in positions.iter().enumerate() {
+        let mut bonus = bonus_at(chars, i);
+        match prev {
+            Some(prev) if prev + 1 == i => {
+                // A run keeps the bonus of the character that started it
+                consecutive_bonus = consecutive_bonus.max(bonus).max(BONUS_CONSECUTIVE);
+                bonus = bonus.max(consecutive_bonus);
+            }
+            Some(prev) => {
+                let gap = (i - prev - 1) as i64;
+                score -= PENALTY_GAP_START + PENALTY_GAP_EXTENSION * (gap - 1);
+                consecutive_bonus = bonus;
+            }
+            None => consecutive_bonus = bonus,
+        }
+        if n == 0 {
+            bonus *= BONUS_FIRST_CHAR_MULTIPLIER;
+        }
+        score += SCORE_MATCH + bonus;
+        prev = Some(i);
+    }
+    score
+}
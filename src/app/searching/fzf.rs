@@ -0,0 +1,68 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use log::info;
+use norganisers_lib::Note;
+
+use super::SearchScores;
+
+// Fuzzy searches a Vec<Note> (label and text) with an `fzf` process, returns the matched ids
+// scored by the order fzf ranked them in. Tags are filtered separately, see `TagFilter`.
+pub fn search(notes: &[Note], note_search: &str) -> anyhow::Result<SearchScores> {
+    if note_search.is_empty() {
+        return Ok(notes.iter().map(|note| (note.id, 0.0)).collect());
+    }
+    let ranked = run_fzf(note_search, notes.iter().map(format_note_line))?.unwrap_or_default();
+    let count = ranked.len();
+    Ok(ranked
+        .into_iter()
+        .enumerate()
+        .map(|(rank, id)| (id, (count - rank) as f64))
+        .collect())
+}
+
+// Returns the matched ids, best match first
+fn run_fzf<I>(filter: &str, lines: I) -> anyhow::Result<Option<Vec<usize>>>
+where
+    I: Iterator<Item = String>,
+{
+    let mut child = Command::new("fzf")
+        .arg("--filter")
+        .arg(filter)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    {
+        let stdin = child.stdin.as_mut().unwrap();
+        for line in lines {
+            if !line.trim().is_empty() {
+                writeln!(stdin, "{}", line)?;
+            }
+        }
+    }
+
+    let output = child.wait_with_output()?;
+    let matched_output = String::from_utf8_lossy(&output.stdout);
+    info!("Searched term: {} | Output: {:?}", filter, matched_output);
+
+    let matched_ids: Vec<usize> = matched_output
+        .lines()
+        .filter_map(|line| line.split(':').next()?.trim().parse().ok())
+        .collect();
+
+    Ok(Some(matched_ids))
+}
+
+// fzf matches lines, so the text of the note is joined into one
+fn format_note_line(note: &Note) -> String {
+    format!(
+        "{}: {} | {} | {}",
+        note.id,
+        note.label,
+        note.text.replace('\n', " "),
+        note.tags.join(" ")
+    )
+}
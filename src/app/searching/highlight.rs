@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+
+use norganisers_lib::{Filter, Note};
+
+use super::{
+    matching::{find_all, scoped_fields, Matcher, NoteField},
+    Search, SearchBackendType, SearchScope, SearchScores,
+};
+
+// The characters of a note that matched a search, as char indices
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Highlights {
+    pub label: Vec<usize>,
+    // Indices into the whole text, lines included
+    pub text: Vec<usize>,
+    // Matched characters of every tag, in the order of `Note.tags`
+    pub tags: Vec<Vec<usize>>,
+}
+
+impl Highlights {
+    // Returns the text line with the most matched characters, with the positions of the matched
+    // characters within that line
+    pub fn snippet<'a>(&self, text: &'a str) -> Option<(&'a str, Vec<usize>)> {
+        lines_with_positions(text, &self.text)
+            .into_iter()
+            .filter(|(_, positions)| !positions.is_empty())
+            .max_by_key(|(_, positions)| positions.len())
+    }
+    fn sort(&mut self) {
+        for positions in [&mut self.label, &mut self.text]
+            .into_iter()
+            .chain(self.tags.iter_mut())
+        {
+            positions.sort_unstable();
+            positions.dedup();
+        }
+    }
+}
+
+// Splits `text` into lines, with the positions that fall on each line made relative to it
+pub fn lines_with_positions<'a>(text: &'a str, positions: &[usize]) -> Vec<(&'a str, Vec<usize>)> {
+    let mut lines = Vec::new();
+    let mut line_start = 0;
+    for line in text.split('\n') {
+        let line_len = line.chars().count();
+        let line_positions = positions
+            .iter()
+            .filter(|&&pos| pos >= line_start && pos < line_start + line_len)
+            .map(|pos| pos - line_start)
+            .collect();
+        lines.push((line.strip_suffix('\r').unwrap_or(line), line_positions));
+        // The newline is a character too
+        line_start += line_len + 1;
+    }
+    lines
+}
+
+// Finds the matched characters of every note in `scores`
+pub fn highlight_matches(
+    notes: &[Note],
+    search: &Search,
+    search_backend: &SearchBackendType,
+    scores: &SearchScores,
+) -> HashMap<usize, Highlights> {
+    notes
+        .iter()
+        .filter(|note| scores.contains_key(&note.id))
+        .map(|note| (note.id, highlight_note(note, search, search_backend)))
+        .collect()
+}
+
+fn highlight_note(note: &Note, search: &Search, search_backend: &SearchBackendType) -> Highlights {
+    let mut highlights = Highlights {
+        tags: vec![Vec::new(); note.tags.len()],
+        ..Default::default()
+    };
+    if let Some(query) = search.query() {
+        for filter in query.positive_filters() {
+            match filter {
+                Filter::Text(text) => {
+                    for term in text.split_whitespace() {
+                        let matcher = Matcher::Fuzzy(term.to_string());
+                        highlight_matcher(&matcher, note, SearchScope::All, &mut highlights);
+                    }
+                }
+                Filter::Label(label) => {
+                    let label: Vec<char> = label.chars().collect();
+                    highlights
+                        .label
+                        .extend(find_all(&label, &note.label, false));
+                }
+                Filter::Body(body) => {
+                    let body: Vec<char> = body.chars().collect();
+                    highlights.text.extend(find_all(&body, &note.text, false));
+                }
+                Filter::Tag(tag) => {
+                    for (i, note_tag) in note.tags.iter().enumerate() {
+                        if note_tag.to_lowercase() == tag.to_lowercase() {
+                            highlights.tags[i].extend(0..note_tag.chars().count());
+                        }
+                    }
+                }
+                // Dates, links and ids have nothing to highlight
+                Filter::Created(..) | Filter::Links(_) | Filter::Id(_) => {}
+            }
+        }
+    } else if search.uses_backend() && *search_backend == SearchBackendType::FullText {
+        for term in norganisers_lib::tokenize(&search.note_search) {
+            highlights
+                .label
+                .extend(find_word_starts(&term, &note.label));
+            highlights.text.extend(find_word_starts(&term, &note.text));
+        }
+    } else if let Ok(matchers) = Matcher::for_search(&search.note_search, search.mode) {
+        for matcher in &matchers {
+            highlight_matcher(matcher, note, search.scope, &mut highlights);
+        }
+    }
+
+    for (tag, positions) in highlights
+        .tags
+        .iter_mut()
+        .zip(search.tag_filter().highlights(note))
+    {
+        tag.extend(positions);
+    }
+    highlights.sort();
+    highlights
+}
+
+fn highlight_matcher(
+    matcher: &Matcher,
+    note: &Note,
+    scope: SearchScope,
+    highlights: &mut Highlights,
+) {
+    for (field, text) in scoped_fields(note, scope) {
+        let positions = match (field, matcher) {
+            (NoteField::Body, Matcher::Fuzzy(_)) => best_line_positions(matcher, text),
+            _ => matcher.find(text).map(|(_, positions)| positions),
+        };
+        let Some(positions) = positions else {
+            continue;
+        };
+        match field {
+            NoteField::Label => highlights.label.extend(positions),
+            NoteField::Body => highlights.text.extend(positions),
+            NoteField::Tag(i) => highlights.tags[i].extend(positions),
+        }
+    }
+}
+
+// A fuzzy match over the whole text can be spread over many lines, so only the line that
+// matches best is highlighted
+fn best_line_positions(matcher: &Matcher, text: &str) -> Option<Vec<usize>> {
+    let mut line_start = 0;
+    let mut best: Option<(i64, Vec<usize>)> = None;
+    for line in text.split('\n') {
+        if let Some((score, positions)) = matcher.find(line) {
+            if best
+                .as_ref()
+                .is_none_or(|(best_score, _)| score > *best_score)
+            {
+                let positions = positions.iter().map(|pos| pos + line_start).collect();
+                best = Some((score, positions));
+            }
+        }
+        line_start += line.chars().count() + 1;
+    }
+    best.map(|(_, positions)| positions)
+}
+
+fn chars_eq_ignore_case(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+// Returns the positions of `term` at the start of every word that starts with it
+fn find_word_starts(term: &str, haystack: &str) -> Vec<usize> {
+    let term: Vec<char> = term.chars().collect();
+    let chars: Vec<char> = haystack.chars().collect();
+    (0..chars.len())
+        .filter(|&start| start == 0 || !chars[start - 1].is_alphanumeric())
+        .filter(|&start| {
+            chars.len() - start >= term.len()
+                && term
+                    .iter()
+                    .zip(&chars[start..])
+                    .all(|(&a, &b)| chars_eq_ignore_case(a, b))
+        })
+        .flat_map(|start| start..start + term.len())
+        .collect()
+}
@@ -0,0 +1,121 @@
+use std::{
+    fs,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use log::warn;
+use tempfile::NamedTempFile;
+
+use super::{fuzzy::fuzzy_match, Search};
+
+// Older searches are forgotten once the history is this long
+const MAX_ENTRIES: usize = 200;
+
+// The searches submitted from the search popup, oldest first, kept in a json file
+#[derive(Debug, Default)]
+pub struct SearchHistory {
+    entries: Vec<Search>,
+    path: Option<PathBuf>,
+    // The recalled entry while cycling through the history in the search popup
+    position: Option<usize>,
+    // The search that was being typed before the history was recalled
+    draft: Option<Search>,
+}
+
+impl SearchHistory {
+    // Reads the history at `path`. A missing file is an empty history, an unreadable one is
+    // logged and replaced on the next save.
+    pub fn load(path: PathBuf) -> Self {
+        let entries = match fs::read_to_string(&path) {
+            Ok(raw) => serde_json::from_str(&raw).unwrap_or_else(|e| {
+                warn!("Could not parse the search history at {:?}: {}", path, e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        Self {
+            entries,
+            path: Some(path),
+            ..Default::default()
+        }
+    }
+    pub fn save(&self) -> anyhow::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        // Replaces the file whole, a failed save keeps the previous history
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let mut writer = BufWriter::new(NamedTempFile::new_in(dir)?);
+        serde_json::to_writer(&mut writer, &self.entries)?;
+        let tmp_file = writer.into_inner()?;
+        tmp_file.as_file().sync_all()?;
+        tmp_file.persist(path)?;
+        Ok(())
+    }
+    // Adds `search` as the newest entry, an earlier identical entry is moved instead
+    pub fn push(&mut self, search: &Search) {
+        self.reset_position();
+        if search.is_empty() {
+            return;
+        }
+        self.entries.retain(|entry| entry != search);
+        self.entries.push(search.clone());
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.drain(..self.entries.len() - MAX_ENTRIES);
+        }
+    }
+    // Steps back to the next older entry. `current` is kept to return to once the newest entry
+    // is passed again.
+    pub fn older(&mut self, current: &Search) -> Option<&Search> {
+        let position = match self.position {
+            None => {
+                self.draft = Some(current.clone());
+                self.entries.len().checked_sub(1)?
+            }
+            Some(position) => position.checked_sub(1)?,
+        };
+        self.position = Some(position);
+        self.entries.get(position)
+    }
+    // Steps forward to the next newer entry, past the newest one the draft comes back
+    pub fn newer(&mut self) -> Option<Search> {
+        let position = self.position?;
+        if position + 1 < self.entries.len() {
+            self.position = Some(position + 1);
+            return self.entries.get(position + 1).cloned();
+        }
+        self.position = None;
+        self.draft.take()
+    }
+    // The entries whose summary fuzzy matches `pattern`, best match first and newest first
+    // among equals. An empty pattern matches every entry.
+    pub fn matching(&self, pattern: &str) -> Vec<Search> {
+        let mut matched: Vec<(i64, usize, &Search)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, search)| {
+                if pattern.trim().is_empty() {
+                    return Some((0, i, search));
+                }
+                let m = fuzzy_match(pattern, &search.summary())?;
+                Some((m.score, i, search))
+            })
+            .collect();
+        matched.sort_by(|(a_score, a_i, _), (b_score, b_i, _)| {
+            b_score.cmp(a_score).then(b_i.cmp(a_i))
+        });
+        matched
+            .into_iter()
+            .map(|(_, _, search)| search.clone())
+            .collect()
+    }
+    pub fn reset_position(&mut self) {
+        self.position = None;
+        self.draft = None;
+    }
+}
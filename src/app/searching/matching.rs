@@ -0,0 +1,228 @@
+use norganisers_lib::Note;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::{fuzzy::fuzzy_match, Search, SearchScores};
+
+// A match in the label or tags says more about a note than the same match in its text
+const LABEL_WEIGHT: i64 = 2;
+const TAG_WEIGHT: i64 = 2;
+const BODY_WEIGHT: i64 = 1;
+
+// The fields of a note the note search looks at
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchScope {
+    #[default]
+    All,
+    Label,
+    Body,
+    Tags,
+}
+
+impl SearchScope {
+    const SCOPES: [SearchScope; 4] = [
+        SearchScope::All,
+        SearchScope::Label,
+        SearchScope::Body,
+        SearchScope::Tags,
+    ];
+    pub fn next(self) -> SearchScope {
+        let idx = Self::SCOPES.iter().position(|&s| s == self).unwrap();
+        Self::SCOPES[(idx + 1) % Self::SCOPES.len()]
+    }
+    pub fn name(self) -> &'static str {
+        match self {
+            SearchScope::All => "all",
+            SearchScope::Label => "label",
+            SearchScope::Body => "body",
+            SearchScope::Tags => "tags",
+        }
+    }
+    fn includes(self, field: NoteField) -> bool {
+        matches!(
+            (self, field),
+            (SearchScope::All, _)
+                | (SearchScope::Label, NoteField::Label)
+                | (SearchScope::Body, NoteField::Body)
+                | (SearchScope::Tags, NoteField::Tag(_))
+        )
+    }
+}
+
+// How the note search is matched against the searched fields
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MatchMode {
+    #[default]
+    Fuzzy,
+    // The whole search as a substring, ignoring case
+    Exact,
+    CaseSensitive,
+    Regex,
+}
+
+impl MatchMode {
+    const MODES: [MatchMode; 4] = [
+        MatchMode::Fuzzy,
+        MatchMode::Exact,
+        MatchMode::CaseSensitive,
+        MatchMode::Regex,
+    ];
+    pub fn next(self) -> MatchMode {
+        let idx = Self::MODES.iter().position(|&m| m == self).unwrap();
+        Self::MODES[(idx + 1) % Self::MODES.len()]
+    }
+    pub fn name(self) -> &'static str {
+        match self {
+            MatchMode::Fuzzy => "fuzzy",
+            MatchMode::Exact => "exact",
+            MatchMode::CaseSensitive => "case-sensitive",
+            MatchMode::Regex => "regex",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteField {
+    Label,
+    Body,
+    // Index into `Note.tags`
+    Tag(usize),
+}
+
+impl NoteField {
+    fn weight(self) -> i64 {
+        match self {
+            NoteField::Label => LABEL_WEIGHT,
+            NoteField::Body => BODY_WEIGHT,
+            NoteField::Tag(_) => TAG_WEIGHT,
+        }
+    }
+}
+
+// Returns the fields of `note` that `scope` covers
+pub fn scoped_fields(note: &Note, scope: SearchScope) -> Vec<(NoteField, &str)> {
+    [
+        (NoteField::Label, note.label.as_str()),
+        (NoteField::Body, note.text.as_str()),
+    ]
+    .into_iter()
+    .chain(
+        note.tags
+            .iter()
+            .enumerate()
+            .map(|(i, tag)| (NoteField::Tag(i), tag.as_str())),
+    )
+    .filter(|(field, _)| scope.includes(*field))
+    .collect()
+}
+
+pub enum Matcher {
+    Fuzzy(String),
+    Exact {
+        needle: Vec<char>,
+        case_sensitive: bool,
+    },
+    Regex(Regex),
+}
+
+impl Matcher {
+    // Fuzzy searches match every whitespace separated term on its own, the other modes match the
+    // whole search
+    pub fn for_search(note_search: &str, mode: MatchMode) -> Result<Vec<Matcher>, regex::Error> {
+        let note_search = note_search.trim();
+        if note_search.is_empty() {
+            return Ok(Vec::new());
+        }
+        let matcher = match mode {
+            MatchMode::Fuzzy => {
+                return Ok(note_search
+                    .split_whitespace()
+                    .map(|term| Matcher::Fuzzy(term.to_string()))
+                    .collect())
+            }
+            MatchMode::Exact | MatchMode::CaseSensitive => Matcher::Exact {
+                needle: note_search.chars().collect(),
+                case_sensitive: mode == MatchMode::CaseSensitive,
+            },
+            MatchMode::Regex => Matcher::Regex(Regex::new(note_search)?),
+        };
+        Ok(vec![matcher])
+    }
+    // Returns the score of the match and the char indices of the matched characters
+    pub fn find(&self, haystack: &str) -> Option<(i64, Vec<usize>)> {
+        let positions = match self {
+            Matcher::Fuzzy(term) => {
+                let m = fuzzy_match(term, haystack)?;
+                return Some((m.score, m.positions));
+            }
+            Matcher::Exact {
+                needle,
+                case_sensitive,
+            } => find_all(needle, haystack, *case_sensitive),
+            Matcher::Regex(regex) => {
+                let char_starts: Vec<usize> = haystack.char_indices().map(|(i, _)| i).collect();
+                let to_char_index =
+                    |byte: usize| char_starts.partition_point(|&start| start < byte);
+                regex
+                    .find_iter(haystack)
+                    .flat_map(|m| to_char_index(m.start())..to_char_index(m.end()))
+                    .collect()
+            }
+        };
+        if positions.is_empty() {
+            None
+        } else {
+            Some((positions.len() as i64, positions))
+        }
+    }
+}
+
+// Every matcher has to match one of the scoped fields, the best field counts
+pub fn match_note(matchers: &[Matcher], note: &Note, scope: SearchScope) -> Option<i64> {
+    let fields = scoped_fields(note, scope);
+    matchers.iter().try_fold(0, |score, matcher| {
+        let best = fields
+            .iter()
+            .filter_map(|(field, text)| Some(matcher.find(text)?.0 * field.weight()))
+            .max()?;
+        Some(score + best)
+    })
+}
+
+// Searches the notes in-process, honouring the scope and match mode of `search`. An invalid
+// regex matches nothing.
+pub fn search(notes: &[Note], search: &Search) -> SearchScores {
+    let Ok(matchers) = Matcher::for_search(&search.note_search, search.mode) else {
+        return SearchScores::new();
+    };
+    notes
+        .iter()
+        .filter_map(|note| {
+            let score = match_note(&matchers, note, search.scope)?;
+            Some((note.id, score as f64))
+        })
+        .collect()
+}
+
+pub fn find_all(needle: &[char], haystack: &str, case_sensitive: bool) -> Vec<usize> {
+    let haystack: Vec<char> = haystack.chars().collect();
+    if needle.is_empty() || needle.len() > haystack.len() {
+        return Vec::new();
+    }
+    let eq =
+        |a: char, b: char| a == b || (!case_sensitive && a.to_lowercase().eq(b.to_lowercase()));
+    let mut positions: Vec<usize> = (0..=haystack.len() - needle.len())
+        .filter(|&start| {
+            needle
+                .iter()
+                .zip(&haystack[start..])
+                .all(|(&a, &b)| eq(a, b))
+        })
+        .flat_map(|start| start..start + needle.len())
+        .collect();
+    // Overlapping occurrences share positions
+    positions.dedup();
+    positions
+}
@@ -0,0 +1,167 @@
+pub mod fuzzy;
+pub mod fzf;
+mod highlight;
+mod history;
+pub mod matching;
+mod tags;
+mod worker;
+
+use std::collections::HashMap;
+
+use log::warn;
+use norganisers_lib::{Filter, Note, Query};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    config::SearchBackendType,
+    note_store::{NoteSortMode, NoteStore},
+};
+
+pub use highlight::{highlight_matches, lines_with_positions, Highlights};
+pub use history::SearchHistory;
+use matching::{match_note, Matcher};
+pub use matching::{MatchMode, SearchScope};
+pub use tags::{TagFilter, TagMode, TagTerm};
+pub use worker::{SearchResults, SearchWorker};
+
+// Note id -> relevance of the match, higher is better
+pub type SearchScores = HashMap<usize, f64>;
+
+// A search as entered in the search popup
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Search {
+    pub note_search: String,
+    pub tag_search: String,
+    pub scope: SearchScope,
+    pub mode: MatchMode,
+}
+
+// A search kept in the config under a name, to be applied again later
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub name: String,
+    pub search: Search,
+    pub sort_mode: NoteSortMode,
+}
+
+impl Search {
+    pub fn is_empty(&self) -> bool {
+        self.note_search.is_empty() && self.tag_search.is_empty()
+    }
+    // One line describing the search, e.g. `quest | tags: any: npc | label, exact`
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.note_search.is_empty() {
+            parts.push(self.note_search.clone());
+        }
+        if !self.tag_search.is_empty() {
+            parts.push(format!("tags: {}", self.tag_search));
+        }
+        if self.scope != SearchScope::All || self.mode != MatchMode::Fuzzy {
+            parts.push(format!("{}, {}", self.scope.name(), self.mode.name()));
+        }
+        parts.join(" | ")
+    }
+    // Parses the note search, if it uses the query language. Queries are only used for fuzzy
+    // searches of every field, other scopes and modes take the search literally.
+    fn query(&self) -> Option<Query> {
+        if !self.uses_backend() {
+            return None;
+        }
+        Query::parse(&self.note_search)
+            .ok()
+            .filter(|query| !query.is_plain_text())
+    }
+    pub fn tag_filter(&self) -> TagFilter {
+        TagFilter::parse(&self.tag_search)
+    }
+    // The configured search backend is only used for fuzzy searches over every field
+    fn uses_backend(&self) -> bool {
+        self.mode == MatchMode::Fuzzy && self.scope == SearchScope::All
+    }
+}
+
+// Matches the text terms of queries
+fn fuzzy_matches_note(text: &str, note: &Note) -> Option<i64> {
+    let matchers = Matcher::for_search(text, MatchMode::Fuzzy).ok()?;
+    match_note(&matchers, note, SearchScope::All)
+}
+
+// Searches the notes with the configured backend, returns the matched ids with their scores. A
+// note search using the query language is evaluated as a query, with its text terms fuzzy
+// matched. The tag search is applied to the results of every backend alike.
+pub fn search_notes(
+    note_store: &NoteStore,
+    search: &Search,
+    search_backend: &SearchBackendType,
+) -> SearchScores {
+    let notes = note_store.get_notes_unfiltered();
+    let scores = match_note_search(note_store, search, search_backend);
+    let tag_filter = search.tag_filter();
+    if tag_filter.is_empty() {
+        return scores;
+    }
+    notes
+        .iter()
+        .filter_map(|note| {
+            let note_score = scores.get(&note.id)?;
+            let tag_score = tag_filter.matches(note)?;
+            Some((note.id, note_score + tag_score as f64))
+        })
+        .collect()
+}
+
+// Matches the note search alone, an empty note search matches every note
+fn match_note_search(
+    note_store: &NoteStore,
+    search: &Search,
+    search_backend: &SearchBackendType,
+) -> SearchScores {
+    let notes = note_store.get_notes_unfiltered();
+    let note_search = search.note_search.as_str();
+    if let Some(query) = search.query() {
+        let text_terms: Vec<&str> = query
+            .positive_filters()
+            .into_iter()
+            .filter_map(|filter| match filter {
+                Filter::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        return notes
+            .iter()
+            .filter(|note| {
+                query.matches_by(note, &|text, note| fuzzy_matches_note(text, note).is_some())
+            })
+            .map(|note| {
+                // Terms in an OR do not all have to match, those that do count
+                let text_score: i64 = text_terms
+                    .iter()
+                    .filter_map(|term| fuzzy_matches_note(term, note))
+                    .sum();
+                (note.id, text_score as f64)
+            })
+            .collect();
+    }
+    if !search.uses_backend() {
+        return matching::search(notes, search);
+    }
+    match search_backend {
+        SearchBackendType::Native => matching::search(notes, search),
+        SearchBackendType::Fzf => match fzf::search(notes, note_search) {
+            Ok(matched) => matched,
+            // Usually fzf is not installed, searching should still work
+            Err(e) => {
+                warn!("fzf search failed, using the native matcher instead: {}", e);
+                matching::search(notes, search)
+            }
+        },
+        SearchBackendType::FullText => {
+            if note_search.trim().is_empty() {
+                return notes.iter().map(|note| (note.id, 0.0)).collect();
+            }
+            note_store.ranked_search(note_search).into_iter().collect()
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use std::fmt;
+
+use norganisers_lib::Note;
+
+use super::fuzzy::fuzzy_match;
+
+// Tag terms written with this prefix are fuzzy matched, every other term has to equal a tag
+const FUZZY_PREFIX: char = '~';
+// An exactly matched tag counts as much as a fuzzy match of a few characters
+const EXACT_TAG_SCORE: i64 = 64;
+
+// How the terms of the tag search combine
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TagMode {
+    // Every term has to match a tag
+    #[default]
+    All,
+    Any,
+    // No term may match a tag
+    None,
+}
+
+impl TagMode {
+    const MODES: [TagMode; 3] = [TagMode::All, TagMode::Any, TagMode::None];
+    pub fn next(self) -> TagMode {
+        let idx = Self::MODES.iter().position(|&m| m == self).unwrap();
+        Self::MODES[(idx + 1) % Self::MODES.len()]
+    }
+    pub fn name(self) -> &'static str {
+        match self {
+            TagMode::All => "all of",
+            TagMode::Any => "any of",
+            TagMode::None => "none of",
+        }
+    }
+    // The prefix that selects the mode in the tag field, e.g. `any: npc, city`
+    fn prefix(self) -> &'static str {
+        match self {
+            TagMode::All => "all:",
+            TagMode::Any => "any:",
+            TagMode::None => "none:",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagTerm {
+    pub tag: String,
+    pub fuzzy: bool,
+}
+
+impl TagTerm {
+    pub fn exact(tag: &str) -> Self {
+        Self {
+            tag: tag.to_string(),
+            fuzzy: false,
+        }
+    }
+    // Returns the score of the match and the matched characters of `tag`
+    fn find(&self, tag: &str) -> Option<(i64, Vec<usize>)> {
+        if self.fuzzy {
+            let m = fuzzy_match(&self.tag, tag)?;
+            Some((m.score, m.positions))
+        } else if self.tag.to_lowercase() == tag.to_lowercase() {
+            Some((EXACT_TAG_SCORE, (0..tag.chars().count()).collect()))
+        } else {
+            None
+        }
+    }
+}
+
+// The parsed tag field of the search popup: comma separated tags, matched exactly ignoring case,
+// optionally prefixed with a mode. `~npc` fuzzy matches a term.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagFilter {
+    pub mode: TagMode,
+    pub terms: Vec<TagTerm>,
+}
+
+impl TagFilter {
+    pub fn parse(tag_search: &str) -> Self {
+        let trimmed = tag_search.trim_start();
+        let (mode, terms) = TagMode::MODES
+            .into_iter()
+            .find_map(|mode| {
+                let prefix = trimmed.get(..mode.prefix().len())?;
+                prefix
+                    .eq_ignore_ascii_case(mode.prefix())
+                    .then(|| (mode, &trimmed[prefix.len()..]))
+            })
+            .unwrap_or((TagMode::All, trimmed));
+        let terms = terms
+            .split(',')
+            .map(str::trim)
+            .filter(|term| !term.is_empty())
+            .filter_map(|term| match term.strip_prefix(FUZZY_PREFIX) {
+                Some(fuzzy) if fuzzy.trim().is_empty() => None,
+                Some(fuzzy) => Some(TagTerm {
+                    tag: fuzzy.trim().to_string(),
+                    fuzzy: true,
+                }),
+                None => Some(TagTerm::exact(term)),
+            })
+            .collect();
+        Self { mode, terms }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+    // Returns the score of `note`, if its tags pass the filter
+    pub fn matches(&self, note: &Note) -> Option<i64> {
+        let term_scores = self.terms.iter().map(|term| {
+            note.tags
+                .iter()
+                .filter_map(|tag| Some(term.find(tag)?.0))
+                .max()
+        });
+        match self.mode {
+            TagMode::All => term_scores.sum(),
+            TagMode::Any => term_scores.flatten().reduce(|a, b| a + b),
+            TagMode::None => term_scores.flatten().next().is_none().then_some(0),
+        }
+    }
+    // Returns the matched characters of every tag of `note`, excluded tags are not highlighted
+    pub fn highlights(&self, note: &Note) -> Vec<Vec<usize>> {
+        note.tags
+            .iter()
+            .map(|tag| {
+                if self.mode == TagMode::None {
+                    return Vec::new();
+                }
+                let mut positions: Vec<usize> = self
+                    .terms
+                    .iter()
+                    .filter_map(|term| term.find(tag))
+                    .flat_map(|(_, positions)| positions)
+                    .collect();
+                positions.sort_unstable();
+                positions.dedup();
+                positions
+            })
+            .collect()
+    }
+}
+
+// Writes the filter back in the form `parse` reads, the default mode is left out
+impl fmt::Display for TagFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.mode != TagMode::All {
+            write!(f, "{} ", self.mode.prefix())?;
+        }
+        let terms: Vec<String> = self
+            .terms
+            .iter()
+            .map(|term| {
+                if term.fuzzy {
+                    format!("{}{}", FUZZY_PREFIX, term.tag)
+                } else {
+                    term.tag.clone()
+                }
+            })
+            .collect();
+        write!(f, "{}", terms.join(","))
+    }
+}
@@ -0,0 +1,155 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use norganisers_lib::Note;
+
+use super::{highlight_matches, search_notes, Highlights, Search, SearchScores};
+use crate::app::{config::SearchBackendType, note_store::NoteStore};
+
+// A search is only started once no newer one came in for this long, so typing a word runs one
+// search instead of one per character
+const DEBOUNCE: Duration = Duration::from_millis(75);
+
+pub struct SearchResults {
+    pub generation: u64,
+    pub scores: SearchScores,
+    pub highlights: HashMap<usize, Highlights>,
+}
+
+enum Request {
+    // Notes added or changed, and the ids of notes removed
+    Changes(Vec<Note>, Vec<usize>),
+    Search {
+        generation: u64,
+        search: Search,
+        search_backend: SearchBackendType,
+    },
+}
+
+// Runs searches on a thread of its own, with its own copy of the notes. Every search gets a new
+// generation, results of older generations are dropped as stale.
+pub struct SearchWorker {
+    requests: Sender<Request>,
+    results: Receiver<SearchResults>,
+    // The generation of the newest search, shared with the thread to skip stale work
+    latest: Arc<AtomicU64>,
+    // The newest search has not returned results yet
+    searching: bool,
+}
+
+impl SearchWorker {
+    pub fn spawn() -> Self {
+        let (requests, request_receiver) = mpsc::channel();
+        let (result_sender, results) = mpsc::channel();
+        let latest = Arc::new(AtomicU64::new(0));
+        let thread_latest = Arc::clone(&latest);
+        thread::spawn(move || run(request_receiver, result_sender, thread_latest));
+        Self {
+            requests,
+            results,
+            latest,
+            searching: false,
+        }
+    }
+    // Starts searching the notes of `note_store`, returns the generation of the search. Only the
+    // notes that changed since the previous search are sent to the thread.
+    pub fn search(
+        &mut self,
+        note_store: &mut NoteStore,
+        search: Search,
+        search_backend: SearchBackendType,
+    ) -> u64 {
+        let (changed, removed) = note_store.take_changes();
+        if !changed.is_empty() || !removed.is_empty() {
+            // A closed channel means the thread is gone, which only happens when the app exits
+            let _ = self.requests.send(Request::Changes(changed, removed));
+        }
+        let generation = self.latest.fetch_add(1, Ordering::SeqCst) + 1;
+        let _ = self.requests.send(Request::Search {
+            generation,
+            search,
+            search_backend,
+        });
+        self.searching = true;
+        generation
+    }
+    // Drops the results of every search started so far
+    pub fn cancel(&mut self) {
+        self.latest.fetch_add(1, Ordering::SeqCst);
+        self.searching = false;
+    }
+    // Returns the results of the newest search, once they are in
+    pub fn try_recv(&mut self) -> Option<SearchResults> {
+        while let Ok(results) = self.results.try_recv() {
+            if results.generation == self.latest.load(Ordering::SeqCst) {
+                self.searching = false;
+                return Some(results);
+            }
+        }
+        None
+    }
+    pub fn is_searching(&self) -> bool {
+        self.searching
+    }
+}
+
+fn run(requests: Receiver<Request>, results: Sender<SearchResults>, latest: Arc<AtomicU64>) {
+    let mut note_store = NoteStore::new(Vec::new());
+    let mut pending: Option<(u64, Search, SearchBackendType)> = None;
+    loop {
+        let request = if pending.is_some() {
+            requests.recv_timeout(DEBOUNCE)
+        } else {
+            requests.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        };
+        let (generation, search, search_backend) = match request {
+            Ok(Request::Changes(changed, removed)) => {
+                note_store.apply_changes(changed, removed);
+                continue;
+            }
+            Ok(Request::Search {
+                generation,
+                search,
+                search_backend,
+            }) => {
+                pending = Some((generation, search, search_backend));
+                continue;
+            }
+            Err(RecvTimeoutError::Timeout) => match pending.take() {
+                Some(pending) => pending,
+                None => continue,
+            },
+            Err(RecvTimeoutError::Disconnected) => return,
+        };
+        let is_stale = || latest.load(Ordering::SeqCst) != generation;
+        if is_stale() {
+            continue;
+        }
+        let scores = search_notes(&note_store, &search, &search_backend);
+        if is_stale() {
+            continue;
+        }
+        let highlights = highlight_matches(
+            note_store.get_notes_unfiltered(),
+            &search,
+            &search_backend,
+            &scores,
+        );
+        let sent = results.send(SearchResults {
+            generation,
+            scores,
+            highlights,
+        });
+        if sent.is_err() {
+            return;
+        }
+    }
+}
@@ -1,13 +1,48 @@
-use std::collections::HashSet;
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    process::Command,
+};
 
-use super::{searching::fzf_search, *};
-use backend::*;
+use super::{
+    cli::run_command,
+    completion::{note_id_candidates, tag_candidates},
+    config::{AppConfig, NoteBackendType, SearchBackendType},
+    event_handling::InputMode,
+    model::{update, Message, Model, RunningState},
+    note_store::{NoteSortMode, NoteStore},
+    searching::{
+        fuzzy::fuzzy_match, fzf, highlight_matches, search_notes, MatchMode, SavedSearch, Search,
+        SearchHistory, SearchScope, SearchWorker, TagFilter, TagMode,
+    },
+    view_components::PopupType,
+};
+use crate::args::{Args, NoteField};
 use chrono::Utc;
+use clap::Parser;
+use norganisers_lib::*;
+use ratatui::{backend::TestBackend, Terminal};
+
+fn fzf_installed() -> bool {
+    Command::new("fzf").arg("--version").output().is_ok()
+}
+
+fn note(id: usize, label: &str, text: &str, tags: &[&str]) -> Note {
+    Note {
+        id,
+        label: label.to_string(),
+        text: text.to_string(),
+        created_at: Utc::now(),
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        related_notes: Vec::new(),
+        revision: 0,
+    }
+}
 
 #[test]
 fn test_retrieve_notes() {
-    let config = AppConfig::load().unwrap();
-    let backend = JsonBackend::new(config.data_file_path);
+    let path: PathBuf = ["test_data", "test.json"].iter().collect();
+    let backend = JsonBackend::new(path);
     let notes = backend.retrieve_notes().unwrap();
     let expected = vec![Note {
         id: 0,
@@ -18,12 +53,17 @@ fn test_retrieve_notes() {
             .unwrap(),
         tags: vec![String::from("npc"), String::from("neverwinter")],
         related_notes: vec![1, 2, 3],
+        revision: 0,
     }];
     assert_eq!(notes, expected)
 }
 
 #[test]
 fn test_fzf_search() {
+    if !fzf_installed() {
+        eprintln!("fzf is not installed, skipping");
+        return;
+    }
     let notes = vec![Note {
         id: 0,
         label: "Testing title".to_string(),
@@ -33,8 +73,666 @@ fn test_fzf_search() {
             .unwrap(),
         tags: vec![String::from("npc"), String::from("neverwinter")],
         related_notes: vec![1, 2, 3],
+        revision: 0,
     }];
     let expected = HashSet::from([0]);
-    let matched_ids = fzf_search(&notes, "long").unwrap();
+    let matched_ids: HashSet<usize> = fzf::search(&notes, "long").unwrap().into_keys().collect();
     assert_eq!(expected, matched_ids)
 }
+
+#[test]
+fn test_fuzzy_match() {
+    let m = fuzzy_match("nvw", "Neverwinter").unwrap();
+    assert_eq!(m.positions, vec![0, 2, 5]);
+    assert!(fuzzy_match("nwv", "Neverwinter").is_none());
+    // Lowercase patterns ignore case, patterns with uppercase do not
+    assert!(fuzzy_match("NEVER", "neverwinter").is_none());
+    assert!(fuzzy_match("never", "NEVERWINTER").is_some());
+    // Every term has to match
+    assert!(fuzzy_match("never water", "Neverwinter").is_none());
+    let m = fuzzy_match("win nev", "Neverwinter").unwrap();
+    assert_eq!(m.positions, vec![0, 1, 2, 5, 6, 7]);
+
+    // Consecutive matches and word starts rank higher than scattered matches
+    let score = |haystack| fuzzy_match("win", haystack).unwrap().score;
+    assert_eq!(score("Winter is coming"), score("the wind"));
+    assert!(score("the wind") > score("what is new"));
+    assert!(score("what is new") > score("twine"));
+}
+
+#[test]
+fn test_native_search() {
+    let notes = vec![
+        note(
+            0,
+            "Testing title",
+            "a very long string",
+            &["npc", "neverwinter"],
+        ),
+        note(1, "Waterdeep", "The city of splendors", &["city"]),
+        note(2, "Luskan", "The city of sails", &["city", "neverwinter"]),
+    ];
+    let note_store = NoteStore::new(notes);
+    let search = |note_search: &str, tag_search: &str| {
+        let search = Search {
+            note_search: note_search.to_string(),
+            tag_search: tag_search.to_string(),
+            ..Default::default()
+        };
+        search_notes(&note_store, &search, &SearchBackendType::Native)
+            .into_keys()
+            .collect::<HashSet<usize>>()
+    };
+    assert_eq!(search("long", ""), HashSet::from([0]));
+    assert_eq!(search("city", ""), HashSet::from([1, 2]));
+    assert_eq!(search("", "~nvr"), HashSet::from([0, 2]));
+    assert_eq!(search("", "city, ~nev"), HashSet::from([2]));
+    assert_eq!(search("sails", "npc"), HashSet::new());
+    // Queries are evaluated, with their text terms fuzzy matched
+    assert_eq!(search("cty -tag:neverwinter", ""), HashSet::from([1]));
+    assert_eq!(search("lng OR label:luskan", ""), HashSet::from([0, 2]));
+    assert_eq!(search("tag:npc OR tag:city", "~nev"), HashSet::from([0, 2]));
+
+    let search = |note_search: &str, tag_search: &str| {
+        let search = Search {
+            note_search: note_search.to_string(),
+            tag_search: tag_search.to_string(),
+            ..Default::default()
+        };
+        search_notes(&note_store, &search, &SearchBackendType::FullText)
+            .into_keys()
+            .collect::<HashSet<usize>>()
+    };
+    assert_eq!(search("city of", ""), HashSet::from([1, 2]));
+    assert_eq!(search("the spl", ""), HashSet::from([1]));
+    // Terms have to start a word
+    assert_eq!(search("ity", ""), HashSet::new());
+    assert_eq!(search("city", "neverwinter"), HashSet::from([2]));
+}
+
+#[test]
+fn test_relevance_sort_mode() {
+    let mut note_store = NoteStore::new(vec![
+        note(0, "what is new", "", &[]),
+        note(1, "twine", "", &[]),
+        note(2, "the wind", "", &[]),
+        note(3, "Luskan", "", &[]),
+    ]);
+    note_store.next_sort_mode();
+    assert!(note_store.current_sort_mode() == NoteSortMode::AscCreated);
+
+    let search = Search {
+        note_search: "win".to_string(),
+        ..Default::default()
+    };
+    let scores = search_notes(&note_store, &search, &SearchBackendType::Native);
+    note_store.update_filter(scores, HashMap::new());
+    assert!(note_store.current_sort_mode() == NoteSortMode::Relevance);
+    let ids: Vec<usize> = note_store.get_notes().iter().map(|note| note.id).collect();
+    assert_eq!(ids, vec![2, 0, 1]);
+
+    // The sort mode from before the search comes back, relevance is skipped without a search
+    note_store.remove_filter();
+    assert!(note_store.current_sort_mode() == NoteSortMode::AscCreated);
+    note_store.prev_sort_mode();
+    note_store.prev_sort_mode();
+    assert!(note_store.current_sort_mode() == NoteSortMode::LabelDesc);
+}
+
+#[test]
+fn test_highlight_matches() {
+    let notes = vec![note(
+        0,
+        "Neverwinter",
+        "First line\nThe wind blows\nwhat is new",
+        &["npc", "neverwinter"],
+    )];
+    let highlight = |note_search: &str, tag_search: &str, backend| {
+        let search = Search {
+            note_search: note_search.to_string(),
+            tag_search: tag_search.to_string(),
+            ..Default::default()
+        };
+        let scores = HashMap::from([(0, 1.0)]);
+        highlight_matches(&notes, &search, &backend, &scores)
+            .remove(&0)
+            .unwrap()
+    };
+
+    let highlights = highlight("win", "~nvr", SearchBackendType::Native);
+    assert_eq!(highlights.label, vec![5, 6, 7]);
+    // Only the best matching line of the text is highlighted
+    assert_eq!(highlights.text, vec![15, 16, 17]);
+    // The note search covers tags as well
+    assert_eq!(highlights.tags, vec![vec![], vec![0, 2, 4, 5, 6, 7]]);
+    let (snippet, positions) = highlights.snippet(&notes[0].text).unwrap();
+    assert_eq!(snippet, "The wind blows");
+    assert_eq!(positions, vec![4, 5, 6]);
+
+    let highlights = highlight("wi bl", "", SearchBackendType::FullText);
+    assert_eq!(highlights.label, Vec::<usize>::new());
+    assert_eq!(highlights.text, vec![15, 16, 20, 21]);
+
+    let highlights = highlight("label:winter OR tag:NPC", "", SearchBackendType::Native);
+    assert_eq!(highlights.label, vec![5, 6, 7, 8, 9, 10]);
+    assert_eq!(highlights.tags, vec![vec![0, 1, 2], vec![]]);
+
+    // Exactly matched tags are highlighted whole, excluded tags not at all
+    let highlights = highlight("", "any: NPC, city", SearchBackendType::Native);
+    assert_eq!(highlights.tags, vec![vec![0, 1, 2], vec![]]);
+    let highlights = highlight("", "none: city", SearchBackendType::Native);
+    assert!(highlights.tags.iter().all(Vec::is_empty));
+}
+
+#[test]
+fn test_tag_filter() {
+    let note_store = NoteStore::new(vec![
+        note(0, "", "", &["npc", "neverwinter"]),
+        note(1, "", "", &["city"]),
+        note(2, "", "", &["city", "neverwinter"]),
+        note(3, "", "", &["snpcx", "n-p-c"]),
+    ]);
+    let search = |tag_search: &str| {
+        let search = Search {
+            tag_search: tag_search.to_string(),
+            ..Default::default()
+        };
+        search_notes(&note_store, &search, &SearchBackendType::Native)
+            .into_keys()
+            .collect::<HashSet<usize>>()
+    };
+    // Tags match exactly, ignoring case, unless a term asks for fuzzy matching
+    assert_eq!(search("npc"), HashSet::from([0]));
+    assert_eq!(search("NPC"), HashSet::from([0]));
+    assert_eq!(search("~npc"), HashSet::from([0, 3]));
+    assert_eq!(search("city, neverwinter"), HashSet::from([2]));
+    assert_eq!(search("all: city, neverwinter"), HashSet::from([2]));
+    assert_eq!(search("any: npc, city"), HashSet::from([0, 1, 2]));
+    assert_eq!(search("None: city,npc"), HashSet::from([3]));
+    assert_eq!(search("any: npc, ~sncx"), HashSet::from([0, 3]));
+
+    let filter = TagFilter::parse(" any:npc, ~nev ,, city");
+    assert_eq!(filter.mode, TagMode::Any);
+    assert_eq!(filter.terms.len(), 3);
+    assert!(filter.terms[1].fuzzy);
+    assert_eq!(filter.to_string(), "any: npc,~nev,city");
+    assert_eq!(TagFilter::parse(&filter.to_string()), filter);
+    assert_eq!(TagFilter::parse("npc").to_string(), "npc");
+}
+
+#[test]
+fn test_search_scopes_and_modes() {
+    let note_store = NoteStore::new(vec![
+        note(
+            0,
+            "Drizzt",
+            "A drow ranger from Menzoberranzan",
+            &["npc", "drow"],
+        ),
+        note(
+            1,
+            "Menzoberranzan",
+            "City of the drow, deep in the Underdark",
+            &["city"],
+        ),
+        note(2, "Journal", "Met a Drow and a dragon (2 of them)", &[]),
+    ]);
+    let search = |note_search: &str, scope, mode| {
+        let search = Search {
+            note_search: note_search.to_string(),
+            scope,
+            mode,
+            ..Default::default()
+        };
+        search_notes(&note_store, &search, &SearchBackendType::Native)
+            .into_keys()
+            .collect::<HashSet<usize>>()
+    };
+    use MatchMode::*;
+    use SearchScope::*;
+    assert_eq!(search("drow", All, Fuzzy), HashSet::from([0, 1, 2]));
+    assert_eq!(search("drow", Label, Fuzzy), HashSet::new());
+    assert_eq!(search("drow", Tags, Fuzzy), HashSet::from([0]));
+    assert_eq!(search("menzo", Label, Fuzzy), HashSet::from([1]));
+    // Fuzzy matching the body finds scattered characters, exact matching does not
+    assert_eq!(search("dnr", Body, Fuzzy), HashSet::from([0, 1, 2]));
+    assert_eq!(search("dnr", Body, Exact), HashSet::new());
+    assert_eq!(search("a drow", All, Exact), HashSet::from([0, 2]));
+    assert_eq!(search("Drow", Body, CaseSensitive), HashSet::from([2]));
+    assert_eq!(search(r"\(\d of", Body, Regex), HashSet::from([2]));
+    assert_eq!(
+        search(r"^(Drizzt|Journal)$", Label, Regex),
+        HashSet::from([0, 2])
+    );
+    // An unfinished regex matches nothing
+    assert_eq!(search(r"(Dri", Label, Regex), HashSet::new());
+    // Queries are only parsed when fuzzy matching every field
+    assert_eq!(search("tag:city", All, Fuzzy), HashSet::from([1]));
+    assert_eq!(search("tag:city", All, Exact), HashSet::new());
+    assert_eq!(search("drizzt OR city", All, Fuzzy), HashSet::from([0, 1]));
+    assert_eq!(search("drizzt OR city", Label, Fuzzy), HashSet::new());
+
+    let search = Search {
+        note_search: "drow".to_string(),
+        scope: Body,
+        mode: Exact,
+        ..Default::default()
+    };
+    let scores = search_notes(&note_store, &search, &SearchBackendType::Native);
+    let highlights = highlight_matches(
+        note_store.get_notes_unfiltered(),
+        &search,
+        &SearchBackendType::Native,
+        &scores,
+    );
+    assert_eq!(highlights[&0].label, Vec::<usize>::new());
+    assert_eq!(highlights[&0].text, vec![2, 3, 4, 5]);
+    assert!(highlights[&0].tags.iter().all(Vec::is_empty));
+    assert_eq!(highlights[&1].text, vec![12, 13, 14, 15]);
+}
+
+#[test]
+fn test_saved_searches() {
+    let saved = SavedSearch {
+        name: "npcs in Neverwinter".to_string(),
+        search: Search {
+            note_search: "tag:npc".to_string(),
+            tag_search: "any: neverwinter, ~nev".to_string(),
+            scope: SearchScope::Label,
+            mode: MatchMode::CaseSensitive,
+        },
+        sort_mode: NoteSortMode::LabelAsc,
+    };
+    let json = serde_json::to_string(&saved).unwrap();
+    assert!(json.contains(r#""mode":"case-sensitive""#));
+    assert!(json.contains(r#""sort_mode":"label-asc""#));
+    assert_eq!(serde_json::from_str::<SavedSearch>(&json).unwrap(), saved);
+
+    // Configs from before saved searches still load
+    let config: AppConfig =
+        serde_json::from_str(r#"{"data_file_path": "notes.json", "note_backend": "json"}"#)
+            .unwrap();
+    assert!(config.saved_searches.is_empty());
+    let search: Search = serde_json::from_str(r#"{"note_search": "quest"}"#).unwrap();
+    assert_eq!(search.scope, SearchScope::All);
+
+    // Saving keeps a `~` in the data path as it was written
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.json");
+    let raw = r#"{"data_file_path": "~/notes/notes.json", "note_backend": "json"}"#;
+    std::fs::write(&config_path, raw).unwrap();
+    let mut config: AppConfig = serde_json::from_str(raw).unwrap();
+    assert!(!config.data_file_path.starts_with("~"));
+    config.saved_searches.push(saved.clone());
+    config.save_to(&config_path).unwrap();
+    let written: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&config_path).unwrap()).unwrap();
+    assert_eq!(written["data_file_path"], "~/notes/notes.json");
+    assert_eq!(written["saved_searches"][0]["name"], "npcs in Neverwinter");
+
+    // Relevance is only kept while a search is applied
+    let mut note_store = NoteStore::new(Vec::new());
+    note_store.set_sort_mode(NoteSortMode::Relevance);
+    assert!(note_store.current_sort_mode() == NoteSortMode::None);
+    note_store.update_filter(HashMap::new(), HashMap::new());
+    note_store.set_sort_mode(NoteSortMode::LabelAsc);
+    assert!(note_store.current_sort_mode() == NoteSortMode::LabelAsc);
+}
+
+#[test]
+fn test_search_history() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("search_history.json");
+    let search = |note_search: &str| Search {
+        note_search: note_search.to_string(),
+        ..Default::default()
+    };
+    let mut history = SearchHistory::load(path.clone());
+    assert!(history.matching("").is_empty());
+    history.push(&search("open quests"));
+    history.push(&search("npc"));
+    history.push(&Search::default());
+    // Searching again moves the search to the front
+    history.push(&search("open quests"));
+    assert_eq!(
+        history.matching(""),
+        vec![search("open quests"), search("npc")]
+    );
+
+    // Cycling back and forth, past the newest entry the draft comes back
+    let draft = search("dra");
+    assert_eq!(history.older(&draft), Some(&search("open quests")));
+    assert_eq!(history.older(&draft), Some(&search("npc")));
+    assert_eq!(history.older(&draft), None);
+    assert_eq!(history.newer(), Some(search("open quests")));
+    assert_eq!(history.newer(), Some(draft));
+    assert_eq!(history.newer(), None);
+
+    history.save().unwrap();
+    let history = SearchHistory::load(path);
+    assert_eq!(history.matching("qst"), vec![search("open quests")]);
+}
+
+#[test]
+fn test_search_worker() {
+    let mut note_store = NoteStore::new(vec![
+        note(0, "Waterdeep", "", &[]),
+        note(1, "Luskan", "", &[]),
+    ]);
+    let search = |note_search: &str| Search {
+        note_search: note_search.to_string(),
+        ..Default::default()
+    };
+    let mut worker = SearchWorker::spawn();
+    let wait_for_results = |worker: &mut SearchWorker| {
+        let start = std::time::Instant::now();
+        while start.elapsed() < std::time::Duration::from_secs(5) {
+            if let Some(results) = worker.try_recv() {
+                return results;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        panic!("the search worker did not answer");
+    };
+
+    // Only the newest of quickly typed searches comes back
+    worker.search(&mut note_store, search("w"), SearchBackendType::Native);
+    let generation = worker.search(&mut note_store, search("lus"), SearchBackendType::Native);
+    assert!(worker.is_searching());
+    let results = wait_for_results(&mut worker);
+    assert_eq!(results.generation, generation);
+    assert_eq!(results.scores.into_keys().collect::<Vec<usize>>(), vec![1]);
+    assert!(results.highlights.contains_key(&1));
+    assert!(!worker.is_searching());
+
+    // Changed notes are sent along with the next search
+    note_store.update_notes(vec![
+        note(0, "Waterdeep", "", &[]),
+        note(2, "Luskan docks", "", &[]),
+    ]);
+    worker.search(&mut note_store, search("lus"), SearchBackendType::Native);
+    let results = wait_for_results(&mut worker);
+    assert_eq!(results.scores.into_keys().collect::<Vec<usize>>(), vec![2]);
+
+    // Cancelled searches never deliver results
+    worker.search(&mut note_store, search("water"), SearchBackendType::Native);
+    worker.cancel();
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    assert!(worker.try_recv().is_none());
+}
+
+#[test]
+fn test_note_store_changes() {
+    let waterdeep = note(0, "Waterdeep", "", &[]);
+    let mut note_store = NoteStore::new(vec![waterdeep.clone(), note(1, "Luskan", "", &[])]);
+    let mut copy = NoteStore::new(Vec::new());
+    let (changed, removed) = note_store.take_changes();
+    assert_eq!(changed.len(), 2);
+    copy.apply_changes(changed, removed);
+    assert_eq!(copy.ranked_search("luskan")[0].0, 1);
+    assert_eq!(note_store.take_changes(), (Vec::new(), Vec::new()));
+
+    // Only what changed is taken, and the copy's index follows it
+    note_store.update_notes(vec![waterdeep, note(2, "Luskan docks", "", &["port"])]);
+    let (changed, removed) = note_store.take_changes();
+    assert_eq!(changed, vec![note_store.get_notes_unfiltered()[1].clone()]);
+    assert_eq!(removed, vec![1]);
+    copy.apply_changes(changed, removed);
+    assert_eq!(
+        copy.get_notes_unfiltered(),
+        note_store.get_notes_unfiltered()
+    );
+    assert_eq!(copy.get_tags(), vec!["port".to_string()]);
+    assert_eq!(copy.ranked_search("luskan")[0].0, 2);
+    assert_eq!(copy.ranked_search("luskan").len(), 1);
+}
+
+#[test]
+fn test_cli_commands() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.json");
+    let blob = NoteBlob {
+        version: BACKEND_VERSION,
+        next_id: 0,
+        notes: Vec::new(),
+    };
+    std::fs::write(&path, serde_json::to_string(&blob).unwrap()).unwrap();
+    let backend = JsonBackend::new(path.clone());
+    let config = AppConfig {
+        data_file_path: path,
+        note_backend: NoteBackendType::Json,
+        search_backend: SearchBackendType::Native,
+        saved_searches: Vec::new(),
+    };
+    let run_piped = |args: &[&str], piped: Option<&str>| -> anyhow::Result<String> {
+        let args = Args::try_parse_from(["norganisers"].iter().chain(args))?;
+        let mut piped = piped.map(str::as_bytes);
+        let piped = piped.as_mut().map(|piped| piped as &mut dyn std::io::Read);
+        let mut out = Vec::new();
+        run_command(&backend, &config, args.command.unwrap(), piped, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    };
+    let run = |args: &[&str]| run_piped(args, None);
+
+    assert_eq!(run(&["add", "Waterdeep", "-t", "city"]).unwrap(), "0\n");
+    assert_eq!(
+        run(&["add", "Luskan", "--text", "North of [[Waterdeep]]"]).unwrap(),
+        "1\n"
+    );
+    assert_eq!(run(&["add", "Neverwinter"]).unwrap(), "2\n");
+    assert_eq!(
+        run(&["list"]).unwrap(),
+        "0: Waterdeep [city]\n1: Luskan\n2: Neverwinter\n"
+    );
+    // Wiki links in the text are linked
+    assert!(run(&["show", "1"]).unwrap().contains("links: 0\n"));
+    // Edits that change nothing are not saved, so the revision stays the same
+    let revision = |id: &str| {
+        let shown = run(&["show", id, "--format", "json"]).unwrap();
+        serde_json::from_str::<serde_json::Value>(&shown).unwrap()["revision"].clone()
+    };
+    let before = revision("1");
+    run(&[
+        "edit",
+        "1",
+        "--label",
+        "Luskan",
+        "--text",
+        "North of [[Waterdeep]]",
+    ])
+    .unwrap();
+    assert_eq!(revision("1"), before);
+
+    run(&["tag", "add", "2", "city", "jewel"]).unwrap();
+    run(&["tag", "remove", "0", "city"]).unwrap();
+    run(&["link", "2", "0"]).unwrap();
+    run(&["edit", "2", "--label", "Neverwinter city"]).unwrap();
+    let shown = run(&["show", "2"]).unwrap();
+    assert!(shown.starts_with("2: Neverwinter city\n"));
+    assert!(shown.contains("tags: city, jewel\n"));
+    assert!(shown.contains("links: 0\n"));
+
+    assert_eq!(
+        run(&["search", "--tags", "city"]).unwrap(),
+        "2: Neverwinter city [city] [jewel]\n"
+    );
+    assert_eq!(run(&["search", "links:0", "lus"]).unwrap(), "1: Luskan\n");
+
+    let json: serde_json::Value =
+        serde_json::from_str(&run(&["list", "--format", "json"]).unwrap()).unwrap();
+    assert_eq!(json[2]["label"], "Neverwinter city");
+    assert_eq!(json[2]["tags"], serde_json::json!(["city", "jewel"]));
+    assert_eq!(json[2]["related_notes"], serde_json::json!([0]));
+    assert!(json[2].get("text").is_none());
+    let shown: serde_json::Value =
+        serde_json::from_str(&run(&["show", "1", "--format", "json"]).unwrap()).unwrap();
+    assert_eq!(shown["text"], "North of [[Waterdeep]]");
+    assert_eq!(
+        run(&["search", "lus", "--format", "tsv", "--fields", "id,text"]).unwrap(),
+        "id\ttext\n1\tNorth of [[Waterdeep]]\n"
+    );
+    assert_eq!(
+        run(&["list", "--format", "jsonl", "--fields", "id"]).unwrap(),
+        "{\"id\":0}\n{\"id\":1}\n{\"id\":2}\n"
+    );
+    // Fields without a format print a table
+    assert_eq!(
+        run(&["list", "--fields", "label,id"]).unwrap(),
+        "label             id\nWaterdeep         0\nLuskan            1\nNeverwinter city  2\n"
+    );
+    assert_eq!(
+        run(&["search", "--tags", "jewel", "--format", "markdown", "--fields", "id,tags"]).unwrap(),
+        "| id | tags |\n|---|---|\n| 2 | city,jewel |\n"
+    );
+
+    // Other notes link to note 0, so removing it needs `--force`
+    assert_eq!(
+        run(&["rm", "0"]).unwrap_err().to_string(),
+        "\"Waterdeep\" is linked from 1: Luskan, 2: Neverwinter city, give --force to delete it \
+         and these links"
+    );
+    assert!(run(&["show", "0"]).is_ok());
+    run(&["rm", "0", "--force"]).unwrap();
+    assert!(run(&["show", "0"]).is_err());
+    assert!(run(&["link", "1", "1"]).is_err());
+    // Links to the removed note are gone
+    assert!(!run(&["show", "2"]).unwrap().contains("links:"));
+
+    let captured = dir.path().join("captured.md");
+    std::fs::write(&captured, "Met in [[Luskan]]\n\n## Mirt\nA moneylender").unwrap();
+    let file = captured.to_str().unwrap();
+    assert_eq!(run(&["add", "--file", file, "-t", "npc"]).unwrap(), "3\n");
+    let shown = run(&["show", "3"]).unwrap();
+    assert!(shown.starts_with("3: Mirt\n"));
+    assert!(shown.contains("tags: npc\n"));
+    assert!(shown.contains("links: 1\n"));
+    assert!(shown.ends_with("\n\nMet in [[Luskan]]\n\n## Mirt\nA moneylender\n"));
+    assert_eq!(
+        run(&["add", "--label", "Mirt", "--file", file]).unwrap(),
+        "4\n"
+    );
+    assert!(run(&["add", "--text", " \n"]).is_err());
+    assert!(run(&["add", "Mirt", "--label", "Mirt"]).is_err());
+    // Piped text is read with `--file -`, or without a label
+    assert_eq!(
+        run_piped(
+            &["add", "--label", "Commit", "--tag", "dev", "--file", "-"],
+            Some("body line\n")
+        )
+        .unwrap(),
+        "5\n"
+    );
+    assert!(run(&["show", "5"]).unwrap().ends_with("\n\nbody line\n\n"));
+    assert_eq!(run_piped(&["add"], Some("Piped\nmore")).unwrap(), "6\n");
+    assert!(run(&["show", "6"]).unwrap().starts_with("6: Piped\n"));
+    // `--text` wins over piped text
+    run_piped(&["add", "Texted", "--text", "given"], Some("piped")).unwrap();
+    assert!(run(&["show", "7"]).unwrap().ends_with("\n\ngiven\n"));
+    // With a label, stdin that is not a terminal is left unread, like in a `while read` loop
+    let mut loop_input = "rest of the loop\n".as_bytes();
+    let args = Args::try_parse_from(["norganisers", "add", "Looped"]).unwrap();
+    let mut out = Vec::new();
+    run_command(
+        &backend,
+        &config,
+        args.command.unwrap(),
+        Some(&mut loop_input),
+        &mut out,
+    )
+    .unwrap();
+    assert_eq!(loop_input, "rest of the loop\n".as_bytes());
+    assert!(run(&["show", "8"]).unwrap().ends_with("\n\n\n"));
+}
+
+// A model on a temporary copy of the test data, with the path of the copy
+fn temp_model() -> (tempfile::TempDir, PathBuf, Model) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.json");
+    std::fs::copy(
+        ["test_data", "test.json"].iter().collect::<PathBuf>(),
+        &path,
+    )
+    .unwrap();
+    let config = AppConfig {
+        data_file_path: path.clone(),
+        note_backend: NoteBackendType::Json,
+        search_backend: SearchBackendType::Native,
+        saved_searches: Vec::new(),
+    };
+    let model = Model::new(JsonBackend::new(path.clone()), config).unwrap();
+    (dir, path, model)
+}
+
+// Updates the model with `msg` and every message that follows from it
+fn send(model: &mut Model, terminal: &mut Terminal<TestBackend>, msg: Message) {
+    let mut msg = Some(msg);
+    while let Some(current) = msg {
+        msg = update(model, terminal, current);
+    }
+}
+
+#[test]
+fn test_pick_note() {
+    let (_dir, path, mut model) = temp_model();
+    model.picking = true;
+    let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+    send(&mut model, &mut terminal, Message::NextNote);
+    // Enter picks the selected note instead of opening the editor
+    send(&mut model, &mut terminal, Message::EditNote);
+    assert!(model.running_state == RunningState::Exit);
+    let picked = model.picked.unwrap();
+    assert_eq!(picked.label, "Testing title");
+    assert_eq!(NoteField::Id.text(&picked), "0");
+    assert_eq!(NoteField::Text.text(&picked), "a very long string");
+    // Nothing was edited
+    assert_eq!(JsonBackend::new(path).retrieve_notes().unwrap()[0], picked);
+}
+
+#[test]
+fn test_close_selection_popup() {
+    let (_dir, _path, mut model) = temp_model();
+    let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+    // Closing the tag selection returns to the popup it was opened from
+    for (popup_type, mode) in [
+        (PopupType::SearchNote, InputMode::SearchPopup),
+        (PopupType::NewNote, InputMode::NewNotePopup),
+    ] {
+        send(&mut model, &mut terminal, Message::OpenPopup(popup_type));
+        send(&mut model, &mut terminal, Message::OpenSelection);
+        assert!(matches!(model.input_mode, InputMode::SelectionPopup));
+        send(&mut model, &mut terminal, Message::CloseSelection);
+        assert_eq!(
+            std::mem::discriminant(&model.input_mode),
+            std::mem::discriminant(&mode)
+        );
+        model.views.popup = None;
+    }
+}
+
+#[test]
+fn test_completion_candidates() {
+    let notes = vec![
+        note(10, "Neverwinter", "", &["city", "sword coast"]),
+        note(2, "Luskan", "", &["city"]),
+        note(7, "Drizzt", "", &[]),
+    ];
+    let ids: Vec<(String, String)> = note_id_candidates(&notes)
+        .iter()
+        .map(|candidate| {
+            (
+                candidate.get_value().to_string_lossy().to_string(),
+                candidate.get_help().unwrap().to_string(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        ids,
+        [("2", "Luskan"), ("7", "Drizzt"), ("10", "Neverwinter")]
+            .map(|(id, label)| (id.to_string(), label.to_string()))
+    );
+    let tags: Vec<String> = tag_candidates(&notes)
+        .iter()
+        .map(|candidate| candidate.get_value().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(tags, ["city", "sword coast"]);
+}
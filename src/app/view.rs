@@ -1,4 +1,4 @@
-use norganisers_lib::NoteBackend;
+use norganisers_lib::{backlinks, NoteBackend};
 use ratatui::{
     layout::{Constraint, Layout},
     Frame,
@@ -7,7 +7,8 @@ use ratatui::{
 use super::{
     model::Model,
     view_components::{
-        InteractiveList, NoteData, PopupData, PopupType, RenderContext, RenderableComponent,
+        InteractiveList, NoteData, PopupData, PopupType, PreviewData, RenderContext,
+        RenderableComponent,
     },
 };
 
@@ -25,24 +26,66 @@ pub fn view<B: NoteBackend>(model: &mut Model<B>, frame: &mut Frame) {
 
     // Text area
     if let Some(note_idx) = model.views.note_list.selected_selection() {
-        let note = &model.note_store.get_note(note_idx).unwrap();
+        let note = model.note_store.get_note(note_idx).unwrap();
+        let preview = PreviewData {
+            note,
+            linked_from: backlinks(model.note_store.get_notes_unfiltered(), note.id),
+            highlights: model.note_store.get_highlights(note.id),
+        };
         model
             .views
             .text_area
-            .render(text_area, frame, Some(RenderContext(note)));
+            .render(text_area, frame, Some(RenderContext(&preview)));
     }
 
     // Popups
     if let Some(popup) = model.views.popup.as_mut() {
         //NOTE: Match popup.popup_type if specific behaviour is needed for a popup type
+        let note_labels: &[&str] = &["Label", "Tags"];
+        let (title, labels, field_help, submit_help) = match popup.popup_type {
+            PopupType::NewNote => (
+                "New note".to_string(),
+                note_labels,
+                "<Tab>/<Shift-Tab> - cycle fields",
+                "<Return> - submit",
+            ),
+            PopupType::EditNote => (
+                "Edit note".to_string(),
+                note_labels,
+                "<Tab>/<Shift-Tab> - cycle fields",
+                "<Return> - submit",
+            ),
+            PopupType::SearchNote => (
+                format!(
+                    "Search in {} ({}), {} tags",
+                    model.search.scope.name(),
+                    model.search.mode.name(),
+                    model.search.tag_filter().mode.name()
+                ),
+                note_labels,
+                "<Tab> - cycle fields, <F2> - fields, <F3> - matching, <F4> - tags",
+                "<Return> - submit, <Ctrl-s> - save, <Up>/<Down>/<Ctrl-r> - history",
+            ),
+            PopupType::SaveSearch => (
+                "Save search".to_string(),
+                &["Name"][..],
+                "<Esc> - cancel",
+                "<Return> - save",
+            ),
+        };
         popup.render(
             main_area,
             frame,
             Some(RenderContext(&PopupData {
-                labels: &["Label", "Tags"],
-                help_texts: &["<Tab>/<Shift-Tab> - cycle fields", "<Return> - submit"],
+                title: &title,
+                labels,
+                help_texts: &[field_help, submit_help],
                 field_data: &["", ""],
             })),
         );
     }
+
+    if let Some(choice_popup) = model.views.choice_popup.as_mut() {
+        choice_popup.render(main_area, frame, None);
+    }
 }
@@ -4,25 +4,56 @@ mod styles;
 mod text_area;
 
 pub use note_list::{NoteData, NoteList};
-pub use popups::{Popup, PopupData, PopupType, SelectionPopupFields};
-use ratatui::{layout::Rect, Frame};
-pub use text_area::TextArea;
-
-use super::forms::Form;
+pub use popups::{ChoicePopup, Popup, PopupData, PopupType, SelectionPopupFields};
+use ratatui::{layout::Rect, style::Style, text::Span, Frame};
+pub use text_area::{PreviewData, TextArea};
 
 pub struct ViewComponents {
     pub note_list: NoteList,
     pub text_area: TextArea,
     pub popup: Option<Popup>,
+    pub choice_popup: Option<ChoicePopup>,
 }
 
 impl Default for ViewComponents {
     fn default() -> Self {
         Self {
             note_list: NoteList::default(),
-            text_area: TextArea::default(),
+            text_area: TextArea,
             popup: None,
+            choice_popup: None,
+        }
+    }
+}
+
+// Splits `text` into spans, styling the characters at `positions` as highlighted
+fn highlighted_spans(text: &str, positions: &[usize], style: Style) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut current = String::new();
+    let mut current_highlighted = false;
+    for (i, c) in text.chars().enumerate() {
+        let highlighted = positions.binary_search(&i).is_ok();
+        if highlighted != current_highlighted && !current.is_empty() {
+            spans.push(span_for(
+                std::mem::take(&mut current),
+                current_highlighted,
+                style,
+            ));
         }
+        current_highlighted = highlighted;
+        current.push(c);
+    }
+    if !current.is_empty() {
+        spans.push(span_for(current, current_highlighted, style));
+    }
+    spans
+}
+
+fn span_for(text: String, highlighted: bool, style: Style) -> Span<'static> {
+    if highlighted {
+        Span::styled(text, style.patch(styles::HIGHLIGHT_STYLE))
+    } else {
+        Span::styled(text, style)
     }
 }
 
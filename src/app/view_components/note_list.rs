@@ -9,8 +9,11 @@ use ratatui::{
 
 use crate::app::note_store::{NoteSortMode, NoteStore};
 
-use super::{styles::SELECTED_STYLE, InteractiveList, RenderContext, RenderableComponent};
+use super::{
+    highlighted_spans, styles::SELECTED_STYLE, InteractiveList, RenderContext, RenderableComponent,
+};
 
+#[derive(Default)]
 pub struct NoteList {
     state: ListState,
 }
@@ -30,14 +33,6 @@ impl InteractiveList for NoteList {
     }
 }
 
-impl Default for NoteList {
-    fn default() -> Self {
-        Self {
-            state: ListState::default(),
-        }
-    }
-}
-
 pub struct NoteData<'a> {
     pub note_store: &'a NoteStore,
 }
@@ -65,34 +60,35 @@ impl<'a> RenderableComponent<'a> for NoteList {
         let items: Vec<ListItem<'_>> = note_store
             .get_notes()
             .iter()
-            .filter_map(|note| {
+            .map(|note| {
                 let mut lines = Vec::new();
+                let highlights = note_store.get_highlights(note.id);
 
                 let max_width = area.width as usize;
                 // LABEL
-                let mut label = format!("{}:{}", note.id, note.label.clone());
+                let id_prefix = format!("{}:", note.id);
+                let mut label = format!("{}{}", id_prefix, note.label.clone());
                 if label.len() > max_width - 5 {
-                    let mut truncated = String::with_capacity(max_width - 5);
-                    let mut current = 0;
-                    for b in label.bytes() {
-                        if current >= max_width.saturating_sub(6) {
-                            break;
-                        }
-                        truncated.push(b as char);
-                        current += 1;
-                    }
+                    let truncated: String =
+                        label.chars().take(max_width.saturating_sub(6)).collect();
                     label = format!("{}…", truncated);
                 }
-
-                let label_span = Span::styled(
-                    label,
-                    Style {
-                        fg: Some(Color::Rgb(160, 195, 245)),
-                        add_modifier: Modifier::UNDERLINED | Modifier::BOLD,
-                        ..Default::default()
-                    },
-                );
-                lines.push(Line::from(vec![label_span]));
+                // Matched positions are in the label, which comes after the id
+                let id_len = id_prefix.chars().count();
+                let label_positions: Vec<usize> = highlights
+                    .map(|h| h.label.iter().map(|pos| pos + id_len).collect())
+                    .unwrap_or_default();
+
+                let label_style = Style {
+                    fg: Some(Color::Rgb(160, 195, 245)),
+                    add_modifier: Modifier::UNDERLINED | Modifier::BOLD,
+                    ..Default::default()
+                };
+                lines.push(Line::from(highlighted_spans(
+                    &label,
+                    &label_positions,
+                    label_style,
+                )));
 
                 // CREATION DATE
                 let creation = note.created_at.to_string();
@@ -111,7 +107,7 @@ impl<'a> RenderableComponent<'a> for NoteList {
                 let mut current_line = Vec::new();
                 let mut current_width = 0;
 
-                for tag in &note.tags {
+                for (i, tag) in note.tags.iter().enumerate() {
                     let tag_text = format!("[{}] ", tag);
                     let tag_width = tag_text.chars().count();
 
@@ -121,8 +117,14 @@ impl<'a> RenderableComponent<'a> for NoteList {
                         current_width = 0;
                     }
 
-                    current_line.push(Span::styled(
-                        tag_text,
+                    // Shifted past the opening bracket
+                    let tag_positions: Vec<usize> = highlights
+                        .and_then(|h| h.tags.get(i))
+                        .map(|positions| positions.iter().map(|pos| pos + 1).collect())
+                        .unwrap_or_default();
+                    current_line.extend(highlighted_spans(
+                        &tag_text,
+                        &tag_positions,
                         Style {
                             fg: Some(Color::Rgb(157, 112, 207)),
                             add_modifier: Modifier::BOLD,
@@ -139,7 +141,12 @@ impl<'a> RenderableComponent<'a> for NoteList {
 
                 lines.extend(tag_lines);
 
-                Some(ListItem::from(lines))
+                // SNIPPET
+                if let Some((line, positions)) = highlights.and_then(|h| h.snippet(&note.text)) {
+                    lines.push(snippet_line(line, &positions, max_width.saturating_sub(2)));
+                }
+
+                ListItem::from(lines)
             })
             .collect();
 
@@ -151,7 +158,7 @@ impl<'a> RenderableComponent<'a> for NoteList {
             };
             format!("Notes - {} found", num_matches)
         } else {
-            format!("Notes")
+            "Notes".to_string()
         };
         let bot_title = match note_store.current_sort_mode() {
             NoteSortMode::None => "<None>",
@@ -159,6 +166,7 @@ impl<'a> RenderableComponent<'a> for NoteList {
             NoteSortMode::LabelDesc => "<Abc ↓ >",
             NoteSortMode::AscCreated => "<Date ↑ >",
             NoteSortMode::DesCreated => "<Date ↓ >",
+            NoteSortMode::Relevance => "<Relevance>",
         };
         let block = Block::new()
             .title(top_title)
@@ -173,3 +181,25 @@ impl<'a> RenderableComponent<'a> for NoteList {
         StatefulWidget::render(list, area, buf, &mut self.state);
     }
 }
+
+// Shows the part of `line` around its first match, fitting in `max_width` characters
+fn snippet_line(line: &str, positions: &[usize], max_width: usize) -> Line<'static> {
+    let first = positions.first().copied().unwrap_or(0);
+    let start = first.saturating_sub(max_width / 3);
+    let mut shown: String = line.chars().skip(start).take(max_width).collect();
+    let mut positions: Vec<usize> = positions
+        .iter()
+        .filter(|&&pos| pos >= start)
+        .map(|pos| pos - start)
+        .collect();
+    if start > 0 {
+        shown.replace_range(..shown.chars().next().map_or(0, char::len_utf8), "…");
+        positions.retain(|&pos| pos > 0);
+    }
+    let style = Style {
+        fg: Some(Color::Rgb(150, 150, 150)),
+        add_modifier: Modifier::ITALIC,
+        ..Default::default()
+    };
+    Line::from(highlighted_spans(shown.trim_end(), &positions, style))
+}
@@ -0,0 +1,93 @@
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    text::{Line, Span},
+    widgets::{
+        Block, Clear, HighlightSpacing, List, ListItem, ListState, Paragraph, StatefulWidget,
+        Widget, Wrap,
+    },
+};
+
+use crate::app::view_components::{
+    styles::SELECTED_STYLE, InteractiveList, RenderContext, RenderableComponent,
+};
+
+use super::popup_area_percentage;
+
+// Asks the user to pick one of a few options, e.g. how to resolve a conflict
+pub struct ChoicePopup {
+    title: String,
+    message: String,
+    choices: Vec<String>,
+    state: ListState,
+}
+
+impl ChoicePopup {
+    pub fn new(title: &str, message: String, choices: &[&str]) -> Self {
+        let mut state = ListState::default();
+        state.select_first();
+        Self {
+            title: title.to_string(),
+            message,
+            choices: choices.iter().map(|choice| choice.to_string()).collect(),
+            state,
+        }
+    }
+}
+
+impl InteractiveList for ChoicePopup {
+    fn reset_selection(&mut self) {
+        self.state.select_first();
+    }
+    fn next_selection(&mut self) {
+        self.state.select_next();
+    }
+    fn prev_selection(&mut self) {
+        self.state.select_previous();
+    }
+    fn selected_selection(&self) -> Option<usize> {
+        self.state.selected()
+    }
+}
+
+impl<'a> RenderableComponent<'a> for ChoicePopup {
+    type ContextData = ();
+    fn render(
+        &mut self,
+        area: ratatui::prelude::Rect,
+        frame: &mut ratatui::Frame,
+        _context: Option<RenderContext<'a, Self::ContextData>>,
+    ) {
+        let buf = frame.buffer_mut();
+        let popup_area = popup_area_percentage(area, 50, 30);
+        let block = Block::bordered().title(self.title.as_str());
+        Widget::render(Clear, popup_area, buf);
+        Widget::render(block, popup_area, buf);
+
+        let [message_area, choice_area, help_area] = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([
+                Constraint::Fill(1),
+                Constraint::Length(self.choices.len() as u16),
+                Constraint::Length(1),
+            ])
+            .areas(popup_area);
+
+        let message = Paragraph::new(self.message.as_str()).wrap(Wrap { trim: true });
+        Widget::render(message, message_area, buf);
+
+        let items: Vec<ListItem> = self
+            .choices
+            .iter()
+            .map(|choice| ListItem::from(Line::from(choice.as_str())))
+            .collect();
+        let list = List::new(items)
+            .highlight_style(SELECTED_STYLE)
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+        StatefulWidget::render(list, choice_area, buf, &mut self.state);
+
+        let help = Paragraph::new(Span::from("<Return> - choose, <Esc> - cancel")).centered();
+        Widget::render(help, help_area, buf);
+    }
+}
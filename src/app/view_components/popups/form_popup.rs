@@ -7,10 +7,11 @@ use ratatui::widgets::{Block, Clear, Paragraph, Widget};
 use ratatui::Frame;
 
 use crate::app::forms::Form;
+use crate::app::searching::TagFilter;
 use crate::app::view_components::{RenderContext, RenderableComponent};
 
 use super::super::InteractiveList;
-use super::{popup_area_percentage, InputCursor, PopupType};
+use super::{byte_index, popup_area_percentage, InputCursor, PopupType};
 use super::{selection::SelectionPopup, SelectionPopupFields};
 
 pub struct PopupState {
@@ -68,9 +69,11 @@ impl Popup {
         self.move_left()
     }
     pub fn replace_selected_field(&mut self, content: &str) {
-        let field = self.state.selected_field;
+        self.replace_field(self.state.selected_field, content);
+    }
+    pub fn replace_field(&mut self, field: usize, content: &str) {
         self.state.form.replace_field_content(field, content);
-        self.move_to_end()
+        self.state.selected_char_indices[field] = content.chars().count();
     }
     pub fn cursors_to_end(&mut self) {
         for i in 0..self.state.form.field_count() {
@@ -100,14 +103,6 @@ impl InputCursor for Popup {
         let new = index.saturating_add(1);
         self.state.selected_char_indices[field] = self.clamp_cursor(new);
     }
-    fn move_to_start(&mut self) {
-        let field = self.state.selected_field;
-        let index = &mut self.state.selected_char_indices[field];
-        if *index == 0 {
-            return;
-        }
-        self.state.selected_char_indices[field] = 0;
-    }
     fn move_to_end(&mut self) {
         let field = self.state.selected_field;
         let index = &mut self.state.selected_char_indices[field];
@@ -124,15 +119,8 @@ impl InputCursor for Popup {
     }
 }
 
-fn byte_index(str: &str, char_index: usize) -> usize {
-    str.char_indices()
-        .map(|(i, _)| i)
-        .nth(char_index)
-        .unwrap_or(str.len())
-}
-
 impl SelectionPopupFields for Popup {
-    fn init_selector(&mut self, items: &Vec<String>) {
+    fn init_selector(&mut self, items: &[String]) {
         self.selection_popup = Some(SelectionPopup::new(items.to_vec()))
     }
     fn close_selector(&mut self) {
@@ -168,6 +156,7 @@ impl SelectionPopupFields for Popup {
 }
 
 pub struct PopupData<'a> {
+    pub title: &'a str,
     pub labels: &'a [&'a str],     // labels for fields
     pub help_texts: &'a [&'a str], // Text for helpers
     pub field_data: &'a [&'a str], // Text to fill in the fields
@@ -180,60 +169,47 @@ impl<'a> RenderableComponent<'a> for Popup {
         frame: &mut Frame,
         context: Option<super::super::RenderContext<'a, Self::ContextData>>,
     ) {
-        let [labels, helpers, field_data] = if let Some(RenderContext(data)) = context {
-            [data.labels, data.help_texts, data.field_data]
+        let (title, [labels, helpers, field_data]) = if let Some(RenderContext(data)) = context {
+            (data.title, [data.labels, data.help_texts, data.field_data])
         } else {
             return;
         };
         let state = &mut self.state;
         let buf = frame.buffer_mut();
 
-        let block = Block::bordered().title("New note");
+        let block = Block::bordered().title(title);
         let popup_area = popup_area_percentage(area, 60, 30);
         Widget::render(Clear, popup_area, buf);
         Widget::render(block, popup_area, buf);
 
+        let field_count = state.form.field_count();
+        // An input for every field, followed by the two help lines
+        let constraints: Vec<Constraint> = (0..field_count)
+            .map(|_| Constraint::Length(3))
+            .chain([Constraint::Length(1), Constraint::Length(1)])
+            .collect();
         let popup_chunks = Layout::default()
             .direction(Direction::Vertical)
             .margin(1)
-            .constraints([
-                Constraint::Length(3), // label input
-                Constraint::Length(3), // tag input
-                Constraint::Length(1), // help field 1
-                Constraint::Length(1), // help field 2
-            ])
+            .constraints(constraints)
             .split(popup_area);
-        let label_text = if state.form.field_content(0).len() == 0 {
-            field_data[0]
-        } else {
-            state.form.field_content(0)
-        };
-        let label_input = Paragraph::new(label_text).block(
-            Block::bordered()
-                .border_style(if state.selected_field == 0 {
-                    Style::new().yellow()
-                } else {
-                    Style::new().white()
-                })
-                .title(labels[0]),
-        );
-
-        Widget::render(label_input, popup_chunks[0], buf);
-        let tags_text = if state.form.field_content(1).len() == 0 {
-            field_data[1]
-        } else {
-            state.form.field_content(1)
-        };
-        let tags_input = Paragraph::new(tags_text).block(
-            Block::bordered()
-                .border_style(if state.selected_field == 1 {
-                    Style::new().yellow()
-                } else {
-                    Style::new().white()
-                })
-                .title(labels[1]),
-        );
-        Widget::render(tags_input, popup_chunks[1], buf);
+        for field in 0..field_count {
+            let text = if state.form.field_content(field).is_empty() {
+                field_data[field]
+            } else {
+                state.form.field_content(field)
+            };
+            let input = Paragraph::new(text).block(
+                Block::bordered()
+                    .border_style(if state.selected_field == field {
+                        Style::new().yellow()
+                    } else {
+                        Style::new().white()
+                    })
+                    .title(labels[field]),
+            );
+            Widget::render(input, popup_chunks[field], buf);
+        }
         //<Tab>/<Shift-Tab> - cycle fields
         //<Return> - submit
         let field_help = Paragraph::new(Span::from(helpers[0]))
@@ -242,23 +218,31 @@ impl<'a> RenderableComponent<'a> for Popup {
         let submit_help = Paragraph::new(Span::from(helpers[1]))
             .block(Block::new())
             .centered();
-        Widget::render(field_help, popup_chunks[2], buf);
-        Widget::render(submit_help, popup_chunks[3], buf);
-        let cursor_pos = match state.selected_field {
-            0 => Position::new(
-                popup_chunks[0].x + state.selected_char_indices[0] as u16 + 1,
-                popup_chunks[0].y + 1,
-            ),
-            1 => Position::new(
-                popup_chunks[1].x + state.selected_char_indices[1] as u16 + 1,
-                popup_chunks[1].y + 1,
-            ),
-            _ => {
-                return;
-            }
-        };
+        Widget::render(field_help, popup_chunks[field_count], buf);
+        Widget::render(submit_help, popup_chunks[field_count + 1], buf);
+        let field = state.selected_field;
+        let cursor_pos = Position::new(
+            popup_chunks[field].x + state.selected_char_indices[field] as u16 + 1,
+            popup_chunks[field].y + 1,
+        );
         if let Some(popup) = self.selection_popup.as_mut() {
-            let tags_in_input: HashSet<&str> = tags_text.split(',').map(str::trim).collect();
+            let tags_text = state.form.field_content(1);
+            // The tag field of the search popup can have a mode and fuzzy terms, which are not tags
+            let tags_in_input: HashSet<String> = if let PopupType::SearchNote = self.popup_type {
+                let filter = TagFilter::parse(tags_text);
+                popup.tag_mode = Some(filter.mode);
+                filter
+                    .terms
+                    .into_iter()
+                    .filter(|term| !term.fuzzy)
+                    .map(|term| term.tag)
+                    .collect()
+            } else {
+                tags_text
+                    .split(',')
+                    .map(|tag| tag.trim().to_string())
+                    .collect()
+            };
             let indices_to_select: Vec<usize> = popup
                 .items
                 .iter()
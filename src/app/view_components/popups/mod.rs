@@ -1,24 +1,29 @@
 use ratatui::layout::{Constraint, Flex, Layout, Rect};
 
+mod choice;
 mod form_popup;
 mod selection;
+pub use choice::ChoicePopup;
 pub use form_popup::{Popup, PopupData};
 
-fn byte_index(str: &str, char_index: usize) -> usize {
+pub(super) fn byte_index(str: &str, char_index: usize) -> usize {
     str.char_indices()
         .map(|(i, _)| i)
         .nth(char_index)
         .unwrap_or(str.len())
 }
 
+#[allow(clippy::enum_variant_names)]
 pub enum PopupType {
     NewNote,
     SearchNote,
     EditNote,
+    // Names the current search to save it
+    SaveSearch,
 }
 
 pub trait SelectionPopupFields {
-    fn init_selector(&mut self, items: &Vec<String>);
+    fn init_selector(&mut self, items: &[String]);
     fn close_selector(&mut self);
     fn next_selection(&mut self);
     fn prev_selection(&mut self);
@@ -30,7 +35,6 @@ pub trait SelectionPopupFields {
 pub trait InputCursor {
     fn move_left(&mut self);
     fn move_right(&mut self);
-    fn move_to_start(&mut self);
     fn move_to_end(&mut self);
     fn clamp_cursor(&self, new_pos: usize) -> usize;
 }
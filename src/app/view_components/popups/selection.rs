@@ -6,6 +6,7 @@ use ratatui::{
     widgets::{Block, Clear, HighlightSpacing, List, ListItem, ListState, StatefulWidget, Widget},
 };
 
+use crate::app::searching::TagMode;
 use crate::app::view_components::{
     styles::SELECTED_STYLE, InteractiveList, RenderContext, RenderableComponent,
 };
@@ -16,6 +17,8 @@ pub struct SelectionPopup {
     pub selected_indices: HashSet<usize>,
     state: ListState,
     pub items: Vec<String>,
+    // Shown in the title when the tags are picked for a search
+    pub tag_mode: Option<TagMode>,
 }
 
 impl SelectionPopup {
@@ -24,6 +27,7 @@ impl SelectionPopup {
             selected_indices: HashSet::default(),
             state: ListState::default(),
             items,
+            tag_mode: None,
         }
     }
     pub fn add_selected_to_selection(&mut self) {
@@ -72,13 +76,12 @@ impl<'a> RenderableComponent<'a> for SelectionPopup {
         &mut self,
         area: ratatui::prelude::Rect,
         frame: &mut ratatui::Frame,
-        context: Option<RenderContext<'a, Self::ContextData>>,
+        _context: Option<RenderContext<'a, Self::ContextData>>,
     ) {
         let buf = frame.buffer_mut();
 
         let max_height: u16 = (area.height / 2) * ITEM_HEIGHT;
         let max_width: u16 = (area.width / 2).saturating_sub(WIDTH_PADDING);
-        let mut height: u16 = max_height;
         let width = area.width / 3;
         let items: Vec<ListItem> = self
             .items
@@ -92,15 +95,10 @@ impl<'a> RenderableComponent<'a> for SelectionPopup {
                 };
                 tag_string = if tag_string.len() > max_width.saturating_sub(WIDTH_PADDING) as usize
                 {
-                    let mut truncated = String::with_capacity(max_width as usize - 5);
-                    let mut current = 0;
-                    for b in tag_string.bytes() {
-                        if current >= max_width.saturating_sub(6) {
-                            break;
-                        }
-                        truncated.push(b as char);
-                        current += 1;
-                    }
+                    let truncated: String = tag_string
+                        .chars()
+                        .take(max_width.saturating_sub(6) as usize)
+                        .collect();
                     format!("{}…", truncated)
                 } else {
                     tag_string
@@ -126,18 +124,22 @@ impl<'a> RenderableComponent<'a> for SelectionPopup {
             })
             .collect();
 
-        let block = Block::bordered().title(format!(
+        let mut title = format!(
             "Tag Selection ({}/{})",
             self.selected_indices.len(),
             items.len()
-        ));
+        );
+        if let Some(tag_mode) = self.tag_mode {
+            title.push_str(&format!(" - {}, <m> - mode", tag_mode.name()));
+        }
+        let block = Block::bordered().title(title);
 
-        height = if items.len() <= 1 {
+        let height = if items.len() <= 1 {
             ITEM_HEIGHT
         } else if items.len() < max_height as usize {
             items.len() as u16
         } else {
-            max_height as u16
+            max_height
         };
         let popup_area = popup_area_length(area, width, height * ITEM_HEIGHT);
         Widget::render(Clear, popup_area, buf);
@@ -1,5 +1,10 @@
-use ratatui::style::{palette::tailwind::SLATE, Modifier, Style};
+use ratatui::style::{
+    palette::tailwind::{AMBER, SLATE},
+    Modifier, Style,
+};
 
 //TODO: Style should be a configuration
 pub const SELECTED_STYLE: Style = Style::new().bg(SLATE.c800).add_modifier(Modifier::BOLD);
+// Characters that matched a search
+pub const HIGHLIGHT_STYLE: Style = Style::new().fg(AMBER.c400).add_modifier(Modifier::BOLD);
 //pub const BG_STYLE: Style = Style::new().bg(SLATE.c100).add_modifier(Modifier::BOLD);
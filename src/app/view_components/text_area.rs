@@ -1,12 +1,16 @@
 use norganisers_lib::Note;
 use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    style::Style,
     symbols,
     text::Line,
     widgets::{Block, Borders, Paragraph, Widget, Wrap},
     Frame,
 };
 
-use super::{RenderContext, RenderableComponent};
+use crate::app::searching::{lines_with_positions, Highlights};
+
+use super::{highlighted_spans, RenderContext, RenderableComponent};
 
 pub struct TextArea;
 
@@ -16,29 +20,85 @@ impl Default for TextArea {
     }
 }
 
+// The previewed note together with the notes that link to it, and what matched the search
+pub struct PreviewData<'a> {
+    pub note: &'a Note,
+    pub linked_from: Vec<&'a Note>,
+    pub highlights: Option<&'a Highlights>,
+}
+
 impl<'a> RenderableComponent<'a> for TextArea {
-    type ContextData = Note;
+    type ContextData = PreviewData<'a>;
     fn render(
         &mut self,
         area: ratatui::prelude::Rect,
         frame: &mut Frame,
         context: Option<super::RenderContext<'a, Self::ContextData>>,
     ) {
-        let note = if let Some(RenderContext(note)) = context {
-            note
+        let PreviewData {
+            note,
+            linked_from,
+            highlights,
+        } = if let Some(RenderContext(data)) = context {
+            data
         } else {
             return;
         };
         let buf = frame.buffer_mut();
-        let lines: Vec<Line> = note.text.lines().map(|line| Line::from(line)).collect();
+
+        // The backlinks panel is only shown when something links to the note
+        let backlinks_height = if linked_from.is_empty() {
+            0
+        } else {
+            linked_from.len() as u16 + 2
+        };
+        let [text_area, backlinks_area] = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Fill(1),
+                Constraint::Max(backlinks_height.min(area.height / 3)),
+            ])
+            .areas(area);
+
+        let no_positions = Vec::new();
+        let (label_positions, text_positions) = match highlights {
+            Some(highlights) => (&highlights.label, &highlights.text),
+            None => (&no_positions, &no_positions),
+        };
+        let mut lines: Vec<Line> = lines_with_positions(&note.text, text_positions)
+            .into_iter()
+            .map(|(line, positions)| {
+                Line::from(highlighted_spans(line, &positions, Style::default()))
+            })
+            .collect();
+        // `lines_with_positions` keeps the empty line after a final newline
+        if note.text.ends_with('\n') {
+            lines.pop();
+        }
         let view_block = Block::default()
-            .title(note.label.clone())
+            .title(Line::from(highlighted_spans(
+                &note.label,
+                label_positions,
+                Style::default(),
+            )))
             .borders(Borders::ALL)
             .border_set(symbols::border::DOUBLE);
         let text_paragraph = Paragraph::new(lines)
             .block(view_block)
             .wrap(Wrap { trim: true });
+        Widget::render(text_paragraph, text_area, buf);
 
-        Widget::render(text_paragraph, area, buf);
+        if !linked_from.is_empty() {
+            let lines: Vec<Line> = linked_from
+                .iter()
+                .map(|other| Line::from(format!("{}: {}", other.id, other.label)))
+                .collect();
+            let backlinks_block = Block::default().title("Linked from").borders(Borders::ALL);
+            Widget::render(
+                Paragraph::new(lines).block(backlinks_block),
+                backlinks_area,
+                buf,
+            );
+        }
     }
 }
@@ -1,17 +1,157 @@
-use std::fs::File;
+use std::{fs::File, path::PathBuf};
 
-use clap::Parser;
-use log::{debug, info};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::{ArgValueCandidates, CompleteEnv};
 use simplelog::*;
 
+use crate::app::{complete_note_ids, complete_tags};
+
 #[derive(Parser, Debug)]
-#[command(author, version, about)]
-struct Args {
+#[command(name = "norganisers_app", author, version, about)]
+pub struct Args {
     #[arg(long)]
-    debug: bool,
+    pub debug: bool,
+    // Without a command the terminal UI is started
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Add a note and print its id
+    Add {
+        /// Without a label the first heading or line of the text is used
+        label: Option<String>,
+        /// The same as the label argument
+        #[arg(long = "label", value_name = "LABEL", conflicts_with = "label")]
+        label_option: Option<String>,
+        /// Without a text, file or label, text piped to stdin is used. Use `--file -` to read
+        /// piped text for a note with a label
+        #[arg(long)]
+        text: Option<String>,
+        /// Read the text from a file, `-` reads stdin
+        #[arg(short, long, conflicts_with = "text")]
+        file: Option<PathBuf>,
+        /// Tag the note, can be given more than once
+        #[arg(short, long = "tag", add = ArgValueCandidates::new(complete_tags))]
+        tags: Vec<String>,
+    },
+    /// List every note
+    List {
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Print a note with its text
+    Show {
+        #[arg(add = ArgValueCandidates::new(complete_note_ids))]
+        id: usize,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Change a note, without options its text is opened in nvim
+    Edit {
+        #[arg(add = ArgValueCandidates::new(complete_note_ids))]
+        id: usize,
+        #[arg(long)]
+        label: Option<String>,
+        #[arg(long)]
+        text: Option<String>,
+    },
+    /// Delete a note, links to it are removed
+    Rm {
+        #[arg(add = ArgValueCandidates::new(complete_note_ids))]
+        id: usize,
+        /// Delete the note even when other notes link to it
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Add or remove tags of a note
+    #[command(subcommand)]
+    Tag(TagCommand),
+    /// Link the note with id `from` to the note with id `to`
+    Link {
+        #[arg(add = ArgValueCandidates::new(complete_note_ids))]
+        from: usize,
+        #[arg(add = ArgValueCandidates::new(complete_note_ids))]
+        to: usize,
+    },
+    /// Choose a note in the terminal UI and print it instead of editing it
+    Pick {
+        /// The field of the chosen note to print
+        #[arg(long, value_enum, default_value = "id")]
+        print: NoteField,
+    },
+    /// Search notes like the search popup does, best match first
+    Search {
+        /// Plain text or a query, e.g. `tag:npc AND NOT tag:dead`
+        query: Vec<String>,
+        /// Tags to filter by, as in the tag field of the search popup
+        #[arg(long, default_value = "")]
+        tags: String,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+}
+
+#[derive(clap::Args, Debug, Clone, Default, PartialEq, Eq)]
+pub struct OutputArgs {
+    /// Print the notes in a format for other tools instead
+    #[arg(long, value_enum)]
+    pub format: Option<OutputFormat>,
+    /// The fields to print, separated by commas. Implies `--format table` if no format is given.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub fields: Vec<NoteField>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// A json array of objects, or a single object for `show`
+    Json,
+    /// One json object per line
+    Jsonl,
+    /// Tab separated values with a header line
+    Tsv,
+    /// Aligned columns
+    Table,
+    /// A markdown table
+    Markdown,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteField {
+    Id,
+    Label,
+    Text,
+    Tags,
+    #[value(name = "created_at")]
+    CreatedAt,
+    #[value(name = "related_notes")]
+    RelatedNotes,
+    Revision,
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum TagCommand {
+    /// Tag a note, tags it already has are skipped
+    Add {
+        #[arg(add = ArgValueCandidates::new(complete_note_ids))]
+        id: usize,
+        #[arg(required = true, add = ArgValueCandidates::new(complete_tags))]
+        tags: Vec<String>,
+    },
+    /// Remove tags from a note
+    Remove {
+        #[arg(add = ArgValueCandidates::new(complete_note_ids))]
+        id: usize,
+        #[arg(required = true, add = ArgValueCandidates::new(complete_tags))]
+        tags: Vec<String>,
+    },
 }
 
-pub fn parse() {
+// Sets up logging and returns the command to run, if any
+pub fn parse() -> Option<Command> {
+    // Answers and exits when called by the shell to complete, e.g. `COMPLETE=bash norganisers_app`
+    CompleteEnv::with_factory(Args::command).complete();
     let args = Args::parse();
     let mut loggers: Vec<Box<dyn SharedLogger>> = Vec::new();
 
@@ -41,4 +181,5 @@ pub fn parse() {
     }
 
     CombinedLogger::init(loggers).unwrap();
+    args.command
 }
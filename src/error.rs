@@ -0,0 +1,55 @@
+use std::{io, path::PathBuf};
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum NoteBackendError {
+    #[error("Note with id {0} not found")]
+    NotFound(usize),
+    #[error("Note with id {0} was changed since it was loaded")]
+    Conflict(usize),
+    #[error("Data file {0:?} does not exist")]
+    MissingFile(PathBuf),
+    #[error("Corrupt note data: {0}")]
+    CorruptData(String),
+    #[error("Note data has version {found}, but only version {supported} is supported")]
+    VersionMismatch { found: usize, supported: usize },
+    #[error("No migration from version {0}")]
+    MissingMigration(usize),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Database(#[from] rusqlite::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum QueryError {
+    #[error("Query ended unexpectedly")]
+    UnexpectedEnd,
+    #[error("Unexpected '{0}' in query")]
+    UnexpectedToken(String),
+    #[error("Missing closing quote in query")]
+    UnterminatedQuote,
+    #[error("Unknown search field '{0}'")]
+    UnknownField(String),
+    #[error("Invalid value '{value}' for search field '{field}'")]
+    InvalidValue { field: String, value: String },
+}
+
+impl From<serde_json::Error> for NoteBackendError {
+    fn from(e: serde_json::Error) -> Self {
+        NoteBackendError::CorruptData(e.to_string())
+    }
+}
+
+impl From<toml::de::Error> for NoteBackendError {
+    fn from(e: toml::de::Error) -> Self {
+        NoteBackendError::CorruptData(e.to_string())
+    }
+}
+
+impl From<toml::ser::Error> for NoteBackendError {
+    fn from(e: toml::ser::Error) -> Self {
+        NoteBackendError::CorruptData(e.to_string())
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::Note;
+
+// BM25 parameters, `K1` limits how much repeating a term helps and `B` how much long notes are
+// penalised
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+// Terms in the label and tags describe a note better than terms in its text
+const LABEL_WEIGHT: f64 = 3.0;
+const TAG_WEIGHT: f64 = 2.0;
+const TEXT_WEIGHT: f64 = 1.0;
+// Query terms also match longer terms that start with them, but count less than an exact match
+const PREFIX_WEIGHT: f64 = 0.5;
+
+// An inverted index over the label, text and tags of notes, ranking results with BM25.
+// Notes are inserted and removed one at a time, so the index never has to be rebuilt.
+#[derive(Debug, Default, Clone)]
+pub struct SearchIndex {
+    // Term -> note id -> weighted term frequency
+    postings: BTreeMap<String, HashMap<usize, f64>>,
+    docs: HashMap<usize, IndexedNote>,
+    total_length: f64,
+}
+
+#[derive(Debug, Clone)]
+struct IndexedNote {
+    length: f64,
+    terms: Vec<String>,
+}
+
+impl SearchIndex {
+    pub fn new(notes: &[Note]) -> Self {
+        let mut index = Self::default();
+        for note in notes {
+            index.insert(note);
+        }
+        index
+    }
+    pub fn len(&self) -> usize {
+        self.docs.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.docs.is_empty()
+    }
+    // Adds `note` to the index, replacing the indexed version of it if there is one
+    pub fn insert(&mut self, note: &Note) {
+        self.remove(note.id);
+        let mut frequencies: HashMap<String, f64> = HashMap::new();
+        let fields = [
+            (note.label.as_str(), LABEL_WEIGHT),
+            (note.text.as_str(), TEXT_WEIGHT),
+        ];
+        let tags = note.tags.iter().map(|tag| (tag.as_str(), TAG_WEIGHT));
+        let mut length = 0.0;
+        for (field, weight) in fields.into_iter().chain(tags) {
+            for term in tokenize(field) {
+                *frequencies.entry(term).or_default() += weight;
+                length += weight;
+            }
+        }
+        for (term, frequency) in &frequencies {
+            self.postings
+                .entry(term.clone())
+                .or_default()
+                .insert(note.id, *frequency);
+        }
+        self.total_length += length;
+        self.docs.insert(
+            note.id,
+            IndexedNote {
+                length,
+                terms: frequencies.into_keys().collect(),
+            },
+        );
+    }
+    pub fn remove(&mut self, id: usize) {
+        let Some(doc) = self.docs.remove(&id) else {
+            return;
+        };
+        self.total_length -= doc.length;
+        for term in doc.terms {
+            if let Some(posting) = self.postings.get_mut(&term) {
+                posting.remove(&id);
+                if posting.is_empty() {
+                    self.postings.remove(&term);
+                }
+            }
+        }
+    }
+    // Returns the ids of the notes that contain every term of `query`, best match first
+    pub fn search(&self, query: &str) -> Vec<(usize, f64)> {
+        let mut scores: Option<HashMap<usize, f64>> = None;
+        for term in tokenize(query) {
+            let term_scores = self.term_scores(&term);
+            scores = Some(match scores {
+                None => term_scores,
+                Some(scores) => scores
+                    .into_iter()
+                    .filter_map(|(id, score)| Some((id, score + term_scores.get(&id)?)))
+                    .collect(),
+            });
+        }
+        let mut ranked: Vec<(usize, f64)> = scores.unwrap_or_default().into_iter().collect();
+        ranked.sort_by(|(a_id, a), (b_id, b)| b.total_cmp(a).then(a_id.cmp(b_id)));
+        ranked
+    }
+    // Scores every note that has `term`, or a term starting with it
+    fn term_scores(&self, term: &str) -> HashMap<usize, f64> {
+        let mut scores: HashMap<usize, f64> = HashMap::new();
+        let matching = self
+            .postings
+            .range(term.to_string()..)
+            .take_while(|(indexed, _)| indexed.starts_with(term));
+        for (indexed, posting) in matching {
+            let weight = if indexed == term { 1.0 } else { PREFIX_WEIGHT };
+            let idf = self.idf(posting.len());
+            for (&id, &frequency) in posting {
+                let score = weight * idf * self.saturate(frequency, self.docs[&id].length);
+                let best = scores.entry(id).or_default();
+                *best = best.max(score);
+            }
+        }
+        scores
+    }
+    fn idf(&self, note_count: usize) -> f64 {
+        let total = self.docs.len() as f64;
+        let with_term = note_count as f64;
+        (1.0 + (total - with_term + 0.5) / (with_term + 0.5)).ln()
+    }
+    fn saturate(&self, frequency: f64, length: f64) -> f64 {
+        let average_length = self.total_length / self.docs.len() as f64;
+        frequency * (K1 + 1.0) / (frequency + K1 * (1.0 - B + B * length / average_length))
+    }
+}
+
+// Splits text into lowercase alphanumeric terms
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(str::to_lowercase)
+}
@@ -1,12 +1,18 @@
 use std::{
-    fs::{self, File},
+    fs::{self, File, OpenOptions},
     io::Write,
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
+use chrono::Utc;
+use log::info;
 use serde_json::Value;
+use tempfile::NamedTempFile;
 
-use crate::{NoteBlob, UnsavedNote, BACKEND_VERSION};
+use crate::{
+    migrations::{blob_version, run_migrations, Migration, MigrationReport, MIGRATIONS},
+    NoteBackendError, NoteBlob, UnsavedNote, BACKEND_VERSION,
+};
 
 use super::{Note, NoteBackend};
 
@@ -20,97 +26,214 @@ impl JsonBackend {
             json_path: file_path,
         }
     }
-}
+    // Upgrades the data file to `BACKEND_VERSION`. The original file is backed up before it is
+    // overwritten.
+    pub fn migrate(&self) -> Result<MigrationReport, NoteBackendError> {
+        self.migrate_with(MIGRATIONS, BACKEND_VERSION)
+    }
+    pub(crate) fn migrate_with(
+        &self,
+        migrations: &[Migration],
+        target_version: usize,
+    ) -> Result<MigrationReport, NoteBackendError> {
+        let _lock = self.lock(true)?;
+        let json_value = read_json_value(&self.json_path)?;
+        let from_version = blob_version(&json_value)?;
+        let mut report = MigrationReport {
+            from_version,
+            to_version: from_version,
+            backup_path: None,
+            applied: Vec::new(),
+        };
+        if from_version == target_version {
+            return Ok(report);
+        }
 
-impl NoteBackend for JsonBackend {
-    fn retrieve_notes(&self) -> anyhow::Result<Vec<Note>> {
-        let json_value = read_json_value(&self.json_path).unwrap();
-        let blob: NoteBlob = serde_json::from_value(json_value)?;
-        if blob.version != BACKEND_VERSION {
-            panic!("")
+        let (migrated, applied) = run_migrations(json_value, migrations, target_version)?;
+        let backup_path = self.backup_path(from_version);
+        fs::copy(&self.json_path, &backup_path)?;
+        info!("Backed up {:?} to {:?}", self.json_path, backup_path);
+        write_json(&self.json_path, &migrated)?;
+        for description in &applied {
+            info!("Applied migration: {}", description);
         }
+
+        report.to_version = target_version;
+        report.backup_path = Some(backup_path);
+        report.applied = applied;
+        Ok(report)
+    }
+    fn backup_path(&self, version: usize) -> PathBuf {
+        let mut file_name = self.json_path.file_name().unwrap_or_default().to_owned();
+        file_name.push(format!(
+            ".v{}-{}.bak",
+            version,
+            Utc::now().format("%Y%m%dT%H%M%S")
+        ));
+        self.json_path.with_file_name(file_name)
+    }
+    // Reads the notes without taking the lock, so no `.lock` file is created. The data file is
+    // only ever replaced whole, so this sees either the old or the new notes.
+    pub fn retrieve_notes_unlocked(&self) -> Result<Vec<Note>, NoteBackendError> {
+        let json_value = read_json_value(&self.json_path)?;
+        let version = blob_version(&json_value)?;
+        if version != BACKEND_VERSION {
+            return Err(NoteBackendError::VersionMismatch {
+                found: version,
+                supported: BACKEND_VERSION,
+            });
+        }
+        let blob: NoteBlob = serde_json::from_value(json_value)?;
         Ok(blob.notes)
     }
-    fn add_note(&self, note: UnsavedNote) -> anyhow::Result<()> {
+    // Guards a read-modify-write cycle against other processes using the same data file. The
+    // lock is taken on a sidecar file, since the data file itself is replaced on every write.
+    // It is released when the returned file is dropped.
+    fn lock(&self, exclusive: bool) -> Result<File, NoteBackendError> {
+        if !self.json_path.exists() {
+            return Err(NoteBackendError::MissingFile(self.json_path.clone()));
+        }
+        let mut file_name = self.json_path.file_name().unwrap_or_default().to_owned();
+        file_name.push(".lock");
+        let lock_file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.json_path.with_file_name(file_name))?;
+        if exclusive {
+            lock_file.lock()?;
+        } else {
+            lock_file.lock_shared()?;
+        }
+        Ok(lock_file)
+    }
+}
+
+impl NoteBackend for JsonBackend {
+    fn retrieve_notes(&self) -> Result<Vec<Note>, NoteBackendError> {
+        let _lock = self.lock(false)?;
+        self.retrieve_notes_unlocked()
+    }
+    fn add_note(&self, note: UnsavedNote) -> Result<usize, NoteBackendError> {
+        let _lock = self.lock(true)?;
         let mut json_value = read_json_value(&self.json_path)?;
 
-        let notes_json = json_value["notes"]
-            .as_array_mut()
-            .ok_or_else(|| anyhow::anyhow!("Missing or invalid 'notes' array"))?;
-        let new_id = get_new_id(notes_json);
+        let next_id = json_value["next_id"].as_u64().ok_or_else(|| {
+            NoteBackendError::CorruptData("Missing or invalid 'next_id' field".to_string())
+        })?;
+        let notes_json = json_value["notes"].as_array_mut().ok_or_else(|| {
+            NoteBackendError::CorruptData("Missing or invalid 'notes' array".to_string())
+        })?;
+        let new_id = get_new_id(notes_json, next_id as usize);
 
-        let new_note = note.into_note(new_id as usize);
+        let new_note = note.into_note(new_id);
         let new_note_value = serde_json::to_value(&new_note)?;
         notes_json.push(new_note_value);
+        json_value["next_id"] = Value::from(new_id + 1);
 
         write_json(&self.json_path, &json_value)?;
 
-        Ok(())
+        Ok(new_id)
     }
-    fn delete_note(&self, target_id: usize) -> anyhow::Result<()> {
+    fn delete_note(&self, note: &Note) -> Result<(), NoteBackendError> {
+        let _lock = self.lock(true)?;
         let mut json_value = read_json_value(&self.json_path)?;
-        let notes_json = json_value["notes"]
-            .as_array_mut()
-            .ok_or_else(|| anyhow::anyhow!("Missing or invalid 'notes' array"))?;
-
-        notes_json.retain(|note| {
-            note.get("id")
-                .and_then(|id| id.as_u64())
-                .map(|id| id as usize != target_id)
-                .unwrap_or(true)
-        });
+        let notes_json = json_value["notes"].as_array_mut().ok_or_else(|| {
+            NoteBackendError::CorruptData("Missing or invalid 'notes' array".to_string())
+        })?;
+
+        let index = find_note_index(notes_json, note)?;
+        notes_json.remove(index);
+
+        for other in notes_json.iter_mut() {
+            let Some(related) = other
+                .get_mut("related_notes")
+                .and_then(|related| related.as_array_mut())
+            else {
+                continue;
+            };
+            let link_count = related.len();
+            related.retain(|id| id.as_u64() != Some(note.id as u64));
+            if related.len() != link_count {
+                let revision = other
+                    .get("revision")
+                    .and_then(|revision| revision.as_u64())
+                    .unwrap_or(0);
+                other["revision"] = Value::from(revision + 1);
+            }
+        }
 
         write_json(&self.json_path, &json_value)?;
         Ok(())
     }
-    fn update_note(&self, note: &Note) -> anyhow::Result<()> {
+    fn update_note(&self, note: &Note) -> Result<(), NoteBackendError> {
+        let _lock = self.lock(true)?;
         let mut json_value = read_json_value(&self.json_path)?;
-        let notes_json = json_value["notes"]
-            .as_array_mut()
-            .ok_or_else(|| anyhow::anyhow!("Missing or invalid 'notes' array"))?;
+        let notes_json = json_value["notes"].as_array_mut().ok_or_else(|| {
+            NoteBackendError::CorruptData("Missing or invalid 'notes' array".to_string())
+        })?;
 
-        let mut updated = false;
-
-        for n in notes_json.iter_mut() {
-            if n.get("id").and_then(|id| id.as_u64()) == Some(note.id as u64) {
-                *n = serde_json::to_value(&note)?;
-                updated = true;
-                break;
-            }
-        }
-
-        if !updated {
-            return Err(anyhow::anyhow!("Note with id {} not found", note.id));
-        }
+        let index = find_note_index(notes_json, note)?;
+        let mut updated_note = note.clone();
+        updated_note.revision += 1;
+        notes_json[index] = serde_json::to_value(&updated_note)?;
 
         write_json(&self.json_path, &json_value)?;
         Ok(())
     }
 }
 
-fn read_json_value(json_path: &PathBuf) -> anyhow::Result<serde_json::Value> {
-    assert!(
-        json_path.exists(),
-        "JSON file {:?} does not exist",
-        json_path
-    );
+fn read_json_value(json_path: &Path) -> Result<serde_json::Value, NoteBackendError> {
+    if !json_path.exists() {
+        return Err(NoteBackendError::MissingFile(json_path.to_path_buf()));
+    }
     let raw = fs::read_to_string(json_path)?;
     let val = serde_json::from_str(&raw)?;
     Ok(val)
 }
 
-fn write_json(json_path: &PathBuf, json_value: &Value) -> anyhow::Result<()> {
+// Writes to a temporary file next to the data file and renames it over the original, so a
+// crash or a full disk never leaves a truncated data file behind
+fn write_json(json_path: &Path, json_value: &Value) -> Result<(), NoteBackendError> {
     let json = serde_json::to_string_pretty(&json_value)?;
-    let mut file = File::create(json_path)?;
-    file.write_all(json.as_bytes())?;
+    let dir = match json_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut tmp_file = NamedTempFile::new_in(dir)?;
+    if let Ok(metadata) = fs::metadata(json_path) {
+        tmp_file.as_file().set_permissions(metadata.permissions())?;
+    }
+    tmp_file.write_all(json.as_bytes())?;
+    tmp_file.as_file().sync_all()?;
+    tmp_file.persist(json_path).map_err(|e| e.error)?;
+    // Make the rename itself durable
+    File::open(dir)?.sync_all()?;
     Ok(())
 }
 
-fn get_new_id(notes_json: &Vec<Value>) -> usize {
-    let max_id = notes_json
+fn find_note_index(notes_json: &[Value], note: &Note) -> Result<usize, NoteBackendError> {
+    let index = notes_json
+        .iter()
+        .position(|n| n.get("id").and_then(|id| id.as_u64()) == Some(note.id as u64))
+        .ok_or(NoteBackendError::NotFound(note.id))?;
+    let stored_revision = notes_json[index]
+        .get("revision")
+        .and_then(|revision| revision.as_u64())
+        .unwrap_or(0);
+    if stored_revision != note.revision as u64 {
+        return Err(NoteBackendError::Conflict(note.id));
+    }
+    Ok(index)
+}
+
+// Uses the stored counter, unless notes with higher ids were added by hand
+fn get_new_id(notes_json: &[Value], next_id: usize) -> usize {
+    let after_max_id = notes_json
         .iter()
         .filter_map(|n| n.get("id").and_then(|id| id.as_u64()))
         .max()
+        .map(|id| id as usize + 1)
         .unwrap_or(0);
-    max_id as usize + 1
+    next_id.max(after_max_id)
 }
@@ -1,22 +1,49 @@
 use chrono::{DateTime, Utc};
 
+mod error;
+mod index;
 mod json;
+mod links;
+mod markdown;
+mod migrations;
+mod query;
+mod sqlite;
 #[cfg(test)]
 mod tests;
+pub use error::{NoteBackendError, QueryError};
+pub use index::{tokenize, SearchIndex};
 pub use json::JsonBackend;
+pub use links::{
+    backlinks, find_dangling_links, parse_wiki_links, repair_dangling_links, resolve_wiki_link,
+    sync_wiki_links, DanglingLink, WikiLink,
+};
+pub use markdown::MarkdownDirBackend;
+pub use migrations::{Migration, MigrationReport, MIGRATIONS};
+pub use query::{Comparison, Filter, Query};
 use serde::{Deserialize, Serialize};
+pub use sqlite::SqliteBackend;
 
-pub const BACKEND_VERSION: usize = 1;
+pub const BACKEND_VERSION: usize = 2;
+// `update_note` and `delete_note` only apply when the stored note still has the revision of
+// the given note, otherwise they return `NoteBackendError::Conflict`. A successful update
+// stores the note with its revision increased by one.
 pub trait NoteBackend {
-    fn retrieve_notes(&self) -> anyhow::Result<Vec<Note>>;
-    fn add_note(&self, note: UnsavedNote) -> anyhow::Result<()>;
-    fn delete_note(&self, target_id: usize) -> anyhow::Result<()>;
-    fn update_note(&self, note: &Note) -> anyhow::Result<()>;
+    fn retrieve_notes(&self) -> Result<Vec<Note>, NoteBackendError>;
+    // Returns the id given to the added note
+    fn add_note(&self, note: UnsavedNote) -> Result<usize, NoteBackendError>;
+    // Also removes the deleted note from the `related_notes` of every other note, so no links
+    // are left dangling
+    fn delete_note(&self, note: &Note) -> Result<(), NoteBackendError>;
+    // Deleting and updating fail with `Conflict` if the stored note has a different `revision`,
+    // that is when someone changed it since it was loaded
+    fn update_note(&self, note: &Note) -> Result<(), NoteBackendError>;
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct NoteBlob {
     pub version: usize,
+    // The id given to the next added note. It only grows, so ids of deleted notes are never reused.
+    pub next_id: usize,
     pub notes: Vec<Note>,
 }
 
@@ -28,6 +55,8 @@ pub struct Note {
     pub created_at: DateTime<Utc>,
     pub tags: Vec<String>,
     pub related_notes: Vec<usize>,
+    #[serde(default)]
+    pub revision: usize,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -55,6 +84,20 @@ impl UnsavedNote {
             created_at,
         }
     }
+    // Builds a note from text captured outside the editor, e.g. piped to the command line.
+    // Without a label the first markdown heading of the text is used, or else its first line.
+    pub fn from_text(
+        label: Option<String>,
+        text: String,
+        tags: Vec<String>,
+        created_at: DateTime<Utc>,
+    ) -> Option<Self> {
+        let label = label
+            .map(|label| label.trim().to_string())
+            .filter(|label| !label.is_empty())
+            .or_else(|| label_from_text(&text))?;
+        Some(Self::new(label, text, tags, Vec::new(), created_at))
+    }
     pub fn into_note(self, id: usize) -> Note {
         Note {
             id,
@@ -63,6 +106,27 @@ impl UnsavedNote {
             tags: self.tags,
             related_notes: self.related_notes,
             created_at: self.created_at,
+            revision: 0,
         }
     }
 }
+
+// Labels taken from a captured text are cut to this many characters
+const MAX_CAPTURED_LABEL: usize = 80;
+
+fn label_from_text(text: &str) -> Option<String> {
+    // Lines of only `#` are empty headings
+    let lines = || {
+        text.lines()
+            .map(str::trim)
+            .filter(|line| !line.trim_start_matches('#').trim().is_empty())
+    };
+    let label = lines()
+        .find_map(|line| {
+            let heading = line.trim_start_matches('#');
+            (heading.len() < line.len() && heading.starts_with(' ')).then(|| heading.trim())
+        })
+        .or_else(|| lines().next())?;
+    let label: String = label.chars().take(MAX_CAPTURED_LABEL).collect();
+    Some(label.trim_end().to_string())
+}
@@ -0,0 +1,144 @@
+use std::{collections::HashSet, fmt};
+
+use crate::{Note, NoteBackend, NoteBackendError};
+
+// A `related_notes` entry of `note_id` that points at a note that does not exist
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DanglingLink {
+    pub note_id: usize,
+    pub missing_id: usize,
+}
+
+// A `[[Label]]` or `[[#id]]` reference written in the text of a note
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WikiLink {
+    Label(String),
+    Id(usize),
+}
+
+impl fmt::Display for WikiLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WikiLink::Label(label) => write!(f, "[[{}]]", label),
+            WikiLink::Id(id) => write!(f, "[[#{}]]", id),
+        }
+    }
+}
+
+// Returns every distinct wiki link in `text`, in the order they first appear
+pub fn parse_wiki_links(text: &str) -> Vec<WikiLink> {
+    let mut links = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("[[") {
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find("]]") else {
+            break;
+        };
+        let inner = rest[..end].trim();
+        // A nested `[[` starts a new link, the outer brackets were not one
+        if inner.contains('[') || inner.contains('\n') {
+            continue;
+        }
+        rest = &rest[end + 2..];
+        let link = match inner.strip_prefix('#').map(str::parse) {
+            Some(Ok(id)) => WikiLink::Id(id),
+            _ if inner.is_empty() => continue,
+            _ => WikiLink::Label(inner.to_string()),
+        };
+        if !links.contains(&link) {
+            links.push(link);
+        }
+    }
+    links
+}
+
+// Returns the id of the note `link` points at. Labels are matched exactly, falling back to
+// ignoring case.
+pub fn resolve_wiki_link(link: &WikiLink, notes: &[Note]) -> Option<usize> {
+    match link {
+        WikiLink::Id(id) => notes.iter().find(|note| note.id == *id).map(|note| note.id),
+        WikiLink::Label(label) => notes
+            .iter()
+            .find(|note| note.label == *label)
+            .or_else(|| {
+                notes
+                    .iter()
+                    .find(|note| note.label.to_lowercase() == label.to_lowercase())
+            })
+            .map(|note| note.id),
+    }
+}
+
+// Brings `note.related_notes` in line with the wiki links in its text. Links that were only
+// written in `previous_text` are removed, links added by other means are kept. Returns the
+// links that do not point at any note.
+pub fn sync_wiki_links(note: &mut Note, previous_text: &str, notes: &[Note]) -> Vec<WikiLink> {
+    let mut unresolved = Vec::new();
+    let mut linked_ids = Vec::new();
+    for link in parse_wiki_links(&note.text) {
+        match resolve_wiki_link(&link, notes) {
+            Some(id) if id != note.id => linked_ids.push(id),
+            Some(_) => {}
+            None => unresolved.push(link),
+        }
+    }
+    let removed_ids: Vec<usize> = parse_wiki_links(previous_text)
+        .iter()
+        .filter_map(|link| resolve_wiki_link(link, notes))
+        .filter(|id| !linked_ids.contains(id))
+        .collect();
+    note.related_notes.retain(|id| !removed_ids.contains(id));
+    for id in linked_ids {
+        if !note.related_notes.contains(&id) {
+            note.related_notes.push(id);
+        }
+    }
+    unresolved
+}
+
+// Returns every other note whose `related_notes` contains `id`
+pub fn backlinks(notes: &[Note], id: usize) -> Vec<&Note> {
+    notes
+        .iter()
+        .filter(|note| note.id != id && note.related_notes.contains(&id))
+        .collect()
+}
+
+pub fn find_dangling_links(notes: &[Note]) -> Vec<DanglingLink> {
+    let ids: HashSet<usize> = notes.iter().map(|note| note.id).collect();
+    notes
+        .iter()
+        .flat_map(|note| {
+            note.related_notes
+                .iter()
+                .filter(|related_id| !ids.contains(related_id))
+                .map(|&missing_id| DanglingLink {
+                    note_id: note.id,
+                    missing_id,
+                })
+        })
+        .collect()
+}
+
+// Removes every dangling link from the stored notes and returns the links that were removed
+pub fn repair_dangling_links<B: NoteBackend>(
+    backend: &B,
+) -> Result<Vec<DanglingLink>, NoteBackendError> {
+    let notes = backend.retrieve_notes()?;
+    let dangling = find_dangling_links(&notes);
+    let missing_ids: HashSet<usize> = dangling.iter().map(|link| link.missing_id).collect();
+    for note in notes {
+        if note
+            .related_notes
+            .iter()
+            .any(|related_id| missing_ids.contains(related_id))
+        {
+            let mut repaired = note;
+            repaired
+                .related_notes
+                .retain(|related_id| !missing_ids.contains(related_id));
+            backend.update_note(&repaired)?;
+        }
+    }
+    Ok(dangling)
+}
@@ -1,6 +1,6 @@
 use anyhow::Result;
 use app::AppConfig;
-use log::info;
+use args::Command;
 use ratatui::{
     crossterm::{
         terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
@@ -9,39 +9,60 @@ use ratatui::{
     prelude::{Backend, CrosstermBackend},
     Terminal,
 };
-use std::{io::stdout, panic};
+use std::{
+    io::{stderr, stdout, Write},
+    panic, process,
+};
 mod app;
 mod args;
 
 pub const APP_VERSION: usize = 1;
 
 fn main() -> Result<()> {
-    args::parse();
-    install_panic_hook();
-    let mut terminal = init_terminal()?;
+    let command = args::parse();
     let config = AppConfig::load()?;
-    app::run_app(&mut terminal, config)?;
-    restore_terminal()?;
+    app::migrate_data(&config)?;
+    let pick = match command {
+        Some(Command::Pick { print }) => Some(print),
+        Some(command) => return app::run_cli(config, command),
+        None => None,
+    };
+    // When picking, the UI is drawn on stderr so stdout only gets the picked note
+    let ui_output: fn() -> Box<dyn Write> = if pick.is_some() {
+        || Box::new(stderr())
+    } else {
+        || Box::new(stdout())
+    };
+    install_panic_hook(ui_output);
+    let mut terminal = init_terminal(ui_output())?;
+    let picked = app::run_app(&mut terminal, config, pick);
+    restore_terminal(ui_output())?;
+    match picked? {
+        Some(picked) => println!("{}", picked),
+        // Nothing was picked, so scripts can tell a cancelled pick apart
+        None if pick.is_some() => process::exit(1),
+        None => {}
+    }
     Ok(())
 }
 
-fn init_terminal() -> anyhow::Result<Terminal<impl Backend>> {
+fn init_terminal(mut output: Box<dyn Write>) -> anyhow::Result<Terminal<impl Backend>> {
     enable_raw_mode()?;
-    stdout().execute(EnterAlternateScreen)?;
-    let terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
+    output.execute(EnterAlternateScreen)?;
+    let terminal = Terminal::new(CrosstermBackend::new(output))?;
     Ok(terminal)
 }
 
-fn restore_terminal() -> anyhow::Result<()> {
-    stdout().execute(LeaveAlternateScreen)?;
+fn restore_terminal(mut output: Box<dyn Write>) -> anyhow::Result<()> {
+    output.execute(LeaveAlternateScreen)?;
     disable_raw_mode()?;
     Ok(())
 }
 
-fn install_panic_hook() {
+fn install_panic_hook(ui_output: fn() -> Box<dyn Write>) {
     let original_hook = panic::take_hook();
     panic::set_hook(Box::new(move |panic_info| {
-        stdout().execute(LeaveAlternateScreen).unwrap();
+        ui_output().execute(LeaveAlternateScreen).unwrap();
         disable_raw_mode().unwrap();
         original_hook(panic_info);
     }));
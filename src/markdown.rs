@@ -0,0 +1,244 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use tempfile::NamedTempFile;
+
+use crate::{Note, NoteBackend, NoteBackendError, UnsavedNote};
+
+const FRONT_MATTER_DELIMITER: &str = "+++";
+// Holds the id given to the next added note, so ids of deleted notes are never reused
+const NEXT_ID_FILE: &str = ".next_id";
+
+// Stores every note as its own markdown file, with the metadata in a TOML front-matter header
+pub struct MarkdownDirBackend {
+    dir_path: PathBuf,
+}
+
+impl MarkdownDirBackend {
+    pub fn new(dir_path: PathBuf) -> Self {
+        Self { dir_path }
+    }
+    fn next_id(&self) -> Result<Option<usize>, NoteBackendError> {
+        let path = self.dir_path.join(NEXT_ID_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let raw = fs::read_to_string(&path)?;
+        raw.trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| NoteBackendError::CorruptData(format!("{:?}: invalid id", path)))
+    }
+    fn note_path(&self, id: usize) -> PathBuf {
+        self.dir_path.join(format!("{}.md", id))
+    }
+    // Files can be renamed outside of the application, so a note is located by its front-matter id
+    fn find_note(&self, id: usize) -> Result<Option<(PathBuf, Note)>, NoteBackendError> {
+        let path = self.note_path(id);
+        if path.exists() {
+            if let Some(note) = read_note(&path)?.filter(|note| note.id == id) {
+                return Ok(Some((path, note)));
+            }
+        }
+        Ok(read_notes(&self.dir_path)?
+            .into_iter()
+            .find(|(_, note)| note.id == id))
+    }
+    fn find_unchanged_note_path(&self, note: &Note) -> Result<PathBuf, NoteBackendError> {
+        let (path, stored) = self
+            .find_note(note.id)?
+            .ok_or(NoteBackendError::NotFound(note.id))?;
+        if stored.revision != note.revision {
+            return Err(NoteBackendError::Conflict(note.id));
+        }
+        Ok(path)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FrontMatter {
+    id: usize,
+    label: String,
+    created_at: DateTime<Utc>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    related_notes: Vec<usize>,
+    #[serde(default)]
+    revision: usize,
+}
+
+impl NoteBackend for MarkdownDirBackend {
+    fn retrieve_notes(&self) -> Result<Vec<Note>, NoteBackendError> {
+        if !self.dir_path.exists() {
+            return Ok(Vec::new());
+        }
+        let mut notes: Vec<Note> = read_notes(&self.dir_path)?
+            .into_iter()
+            .map(|(_, note)| note)
+            .collect();
+        notes.sort_by_key(|note| note.id);
+        Ok(notes)
+    }
+    fn add_note(&self, note: UnsavedNote) -> Result<usize, NoteBackendError> {
+        fs::create_dir_all(&self.dir_path)?;
+        let after_max_id = self
+            .retrieve_notes()?
+            .iter()
+            .map(|note| note.id)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        // Notes with higher ids than the counter can have been added by hand
+        let mut new_id = self.next_id()?.unwrap_or(0).max(after_max_id);
+        // A file named after the id can be there without holding a note
+        while self.note_path(new_id).exists() {
+            new_id += 1;
+        }
+        let new_note = note.into_note(new_id);
+        temp_file(&self.note_path(new_id), &to_markdown(&new_note)?)?
+            .persist_noclobber(self.note_path(new_id))
+            .map_err(|e| e.error)?;
+        write_file(&self.dir_path.join(NEXT_ID_FILE), &(new_id + 1).to_string())?;
+        Ok(new_id)
+    }
+    fn delete_note(&self, note: &Note) -> Result<(), NoteBackendError> {
+        let path = self.find_unchanged_note_path(note)?;
+        fs::remove_file(path)?;
+
+        for (path, mut other) in read_notes(&self.dir_path)? {
+            if other.related_notes.contains(&note.id) {
+                other.related_notes.retain(|&id| id != note.id);
+                other.revision += 1;
+                write_file(&path, &to_markdown(&other)?)?;
+            }
+        }
+        Ok(())
+    }
+    fn update_note(&self, note: &Note) -> Result<(), NoteBackendError> {
+        let path = self.find_unchanged_note_path(note)?;
+        let mut updated_note = note.clone();
+        updated_note.revision += 1;
+        write_file(&path, &to_markdown(&updated_note)?)?;
+        Ok(())
+    }
+}
+
+// Writes `contents` next to `path`, to be moved in place once it is complete
+fn temp_file(path: &Path, contents: &str) -> Result<NamedTempFile, NoteBackendError> {
+    let mut tmp_file = NamedTempFile::new_in(path.parent().unwrap_or(Path::new(".")))?;
+    if let Ok(metadata) = fs::metadata(path) {
+        tmp_file.as_file().set_permissions(metadata.permissions())?;
+    }
+    tmp_file.write_all(contents.as_bytes())?;
+    tmp_file.as_file().sync_all()?;
+    Ok(tmp_file)
+}
+
+// Replaces `path` whole, so a failed write leaves the old file intact
+fn write_file(path: &Path, contents: &str) -> Result<(), NoteBackendError> {
+    temp_file(path, contents)?
+        .persist(path)
+        .map_err(|e| e.error)?;
+    Ok(())
+}
+
+fn note_paths(dir_path: &Path) -> Result<Vec<PathBuf>, NoteBackendError> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir_path)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "md") {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+// The notes of every file in the directory that holds one
+fn read_notes(dir_path: &Path) -> Result<Vec<(PathBuf, Note)>, NoteBackendError> {
+    let mut notes = Vec::new();
+    for path in note_paths(dir_path)? {
+        if let Some(note) = read_note(&path)? {
+            notes.push((path, note));
+        }
+    }
+    Ok(notes)
+}
+
+// Other markdown files can share the directory, e.g. a README, so files that are not notes are
+// skipped with a warning instead of failing every read
+fn read_note(path: &Path) -> Result<Option<Note>, NoteBackendError> {
+    let raw = match fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+            warn!("Skipping {:?}, it is not valid UTF-8", path);
+            return Ok(None);
+        }
+        Err(e) => return Err(e.into()),
+    };
+    match from_markdown(&raw) {
+        Ok(note) => Ok(Some(note)),
+        Err(NoteBackendError::CorruptData(reason)) => {
+            warn!("Skipping {:?}, it is not a note: {}", path, reason);
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn to_markdown(note: &Note) -> Result<String, NoteBackendError> {
+    let front_matter = toml::to_string(&FrontMatter {
+        id: note.id,
+        label: note.label.clone(),
+        created_at: note.created_at,
+        tags: note.tags.clone(),
+        related_notes: note.related_notes.clone(),
+        revision: note.revision,
+    })?;
+    Ok(format!(
+        "{delim}\n{}{delim}\n{}",
+        front_matter,
+        note.text,
+        delim = FRONT_MATTER_DELIMITER
+    ))
+}
+
+fn from_markdown(raw: &str) -> Result<Note, NoteBackendError> {
+    // Editors on Windows can save the file with CRLF line endings
+    let rest = raw
+        .strip_prefix(FRONT_MATTER_DELIMITER)
+        .and_then(|rest| {
+            rest.strip_prefix("\r\n")
+                .or_else(|| rest.strip_prefix('\n'))
+        })
+        .ok_or_else(|| NoteBackendError::CorruptData("Missing front matter".to_string()))?;
+    // The closing delimiter is a line of its own, followed by the body or the end of the file
+    let mut header_len = 0;
+    let mut body_start = None;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end_matches(['\r', '\n']) == FRONT_MATTER_DELIMITER {
+            body_start = Some(header_len + line.len());
+            break;
+        }
+        header_len += line.len();
+    }
+    let body_start = body_start
+        .ok_or_else(|| NoteBackendError::CorruptData("Unterminated front matter".to_string()))?;
+    let (header, body) = (&rest[..header_len], &rest[body_start..]);
+    let front_matter: FrontMatter = toml::from_str(header)?;
+    Ok(Note {
+        id: front_matter.id,
+        label: front_matter.label,
+        text: body.to_string(),
+        created_at: front_matter.created_at,
+        tags: front_matter.tags,
+        related_notes: front_matter.related_notes,
+        revision: front_matter.revision,
+    })
+}
@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+
+use serde_json::Value;
+
+use crate::NoteBackendError;
+
+// Upgrades a note blob from `from_version` to `from_version + 1`. Migrations work on the raw
+// json, since older blobs do not have to deserialize into the current `NoteBlob`.
+pub struct Migration {
+    pub from_version: usize,
+    pub description: &'static str,
+    pub migrate: fn(Value) -> Result<Value, NoteBackendError>,
+}
+
+// Every migration between the first format and `BACKEND_VERSION`, in order
+pub const MIGRATIONS: &[Migration] = &[Migration {
+    from_version: 1,
+    description: "add a next_id counter, so ids of deleted notes are not reused",
+    migrate: add_next_id,
+}];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationReport {
+    pub from_version: usize,
+    pub to_version: usize,
+    pub backup_path: Option<PathBuf>,
+    pub applied: Vec<&'static str>,
+}
+
+impl MigrationReport {
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty()
+    }
+}
+
+pub fn blob_version(blob: &Value) -> Result<usize, NoteBackendError> {
+    blob.get("version")
+        .and_then(|version| version.as_u64())
+        .map(|version| version as usize)
+        .ok_or_else(|| {
+            NoteBackendError::CorruptData("Missing or invalid 'version' field".to_string())
+        })
+}
+
+// Applies migrations one version at a time until the blob reaches `target_version`.
+// Returns the migrated blob together with the descriptions of the migrations that ran.
+pub fn run_migrations(
+    mut blob: Value,
+    migrations: &[Migration],
+    target_version: usize,
+) -> Result<(Value, Vec<&'static str>), NoteBackendError> {
+    let mut version = blob_version(&blob)?;
+    if version > target_version {
+        return Err(NoteBackendError::VersionMismatch {
+            found: version,
+            supported: target_version,
+        });
+    }
+    let mut applied = Vec::new();
+    while version < target_version {
+        let migration = migrations
+            .iter()
+            .find(|migration| migration.from_version == version)
+            .ok_or(NoteBackendError::MissingMigration(version))?;
+        blob = (migration.migrate)(blob)?;
+        version += 1;
+        blob["version"] = Value::from(version);
+        applied.push(migration.description);
+    }
+    Ok((blob, applied))
+}
+
+fn add_next_id(mut blob: Value) -> Result<Value, NoteBackendError> {
+    let max_id = blob["notes"]
+        .as_array()
+        .ok_or_else(|| {
+            NoteBackendError::CorruptData("Missing or invalid 'notes' array".to_string())
+        })?
+        .iter()
+        .filter_map(|note| note.get("id").and_then(|id| id.as_u64()))
+        .max()
+        .unwrap_or(0);
+    blob["next_id"] = Value::from(max_id + 1);
+    Ok(blob)
+}
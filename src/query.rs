@@ -0,0 +1,323 @@
+use std::{iter::Peekable, str::Chars};
+
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::{Note, QueryError};
+
+// A parsed search query, e.g. `tag:npc AND NOT tag:dead label:"Neverwinter" created:>2024-01-01`.
+// Terms next to each other are joined with AND, which binds tighter than OR.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query {
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+    Filter(Filter),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    // Plain text, searched for in the label and text of a note
+    Text(String),
+    Label(String),
+    Body(String),
+    // Matches a tag exactly, ignoring case
+    Tag(String),
+    Created(Comparison, NaiveDate),
+    // The note links to the note with this id
+    Links(usize),
+    Id(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Query, QueryError> {
+        let tokens = lex(input)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let query = parser.parse_or()?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(query),
+            Some(token) => Err(QueryError::UnexpectedToken(token.to_string())),
+        }
+    }
+    // True if the query has nothing but text filters joined with AND
+    pub fn is_plain_text(&self) -> bool {
+        match self {
+            Query::And(queries) => queries.iter().all(Query::is_plain_text),
+            Query::Filter(Filter::Text(_)) => true,
+            _ => false,
+        }
+    }
+    // Returns the filters that are not negated, in order
+    pub fn positive_filters(&self) -> Vec<&Filter> {
+        match self {
+            Query::And(queries) | Query::Or(queries) => {
+                queries.iter().flat_map(Query::positive_filters).collect()
+            }
+            Query::Not(_) => Vec::new(),
+            Query::Filter(filter) => vec![filter],
+        }
+    }
+    // Text filters match if the label or text contains them, ignoring case
+    pub fn matches(&self, note: &Note) -> bool {
+        self.matches_by(note, &|text, note| {
+            let text = text.to_lowercase();
+            note.label.to_lowercase().contains(&text) || note.text.to_lowercase().contains(&text)
+        })
+    }
+    // Like `matches`, but text filters are matched by `text_matcher`, e.g. a fuzzy matcher
+    pub fn matches_by(&self, note: &Note, text_matcher: &dyn Fn(&str, &Note) -> bool) -> bool {
+        match self {
+            Query::And(queries) => queries.iter().all(|q| q.matches_by(note, text_matcher)),
+            Query::Or(queries) => queries.iter().any(|q| q.matches_by(note, text_matcher)),
+            Query::Not(query) => !query.matches_by(note, text_matcher),
+            Query::Filter(Filter::Text(text)) => text_matcher(text, note),
+            Query::Filter(filter) => filter.matches(note),
+        }
+    }
+    pub fn filter<'a>(&self, notes: &'a [Note]) -> Vec<&'a Note> {
+        notes.iter().filter(|note| self.matches(note)).collect()
+    }
+}
+
+impl Filter {
+    fn matches(&self, note: &Note) -> bool {
+        let contains =
+            |haystack: &str, needle: &str| haystack.to_lowercase().contains(&needle.to_lowercase());
+        match self {
+            Filter::Text(text) => contains(&note.label, text) || contains(&note.text, text),
+            Filter::Label(label) => contains(&note.label, label),
+            Filter::Body(body) => contains(&note.text, body),
+            Filter::Tag(tag) => note
+                .tags
+                .iter()
+                .any(|t| t.to_lowercase() == tag.to_lowercase()),
+            Filter::Created(comparison, date) => {
+                comparison.holds(note.created_at.date_naive(), *date)
+            }
+            Filter::Links(id) => note.related_notes.contains(id),
+            Filter::Id(id) => note.id == *id,
+        }
+    }
+}
+
+impl Comparison {
+    fn holds(self, left: NaiveDate, right: NaiveDate) -> bool {
+        match self {
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Equal => left == right,
+            Comparison::GreaterOrEqual => left >= right,
+            Comparison::Greater => left > right,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    And,
+    Or,
+    Not,
+    LeftParen,
+    RightParen,
+    Word(String),
+    // `field:value`
+    Field(String, String),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::And => write!(f, "AND"),
+            Token::Or => write!(f, "OR"),
+            Token::Not => write!(f, "NOT"),
+            Token::LeftParen => write!(f, "("),
+            Token::RightParen => write!(f, ")"),
+            Token::Word(word) => write!(f, "{}", word),
+            Token::Field(field, value) => write!(f, "{}:{}", field, value),
+        }
+    }
+}
+
+fn lex(input: &str) -> Result<Vec<Token>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LeftParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RightParen);
+            }
+            '"' => {
+                chars.next();
+                tokens.push(Token::Word(lex_quoted(&mut chars)?));
+            }
+            '-' => {
+                chars.next();
+                tokens.push(Token::Not);
+            }
+            _ => {
+                let word = lex_word(&mut chars);
+                let token = match word.as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => match word.split_once(':') {
+                        Some((field, "")) if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            Token::Field(field.to_string(), lex_quoted(&mut chars)?)
+                        }
+                        Some((field, value)) => Token::Field(field.to_string(), value.to_string()),
+                        None => Token::Word(word),
+                    },
+                };
+                tokens.push(token);
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn lex_word(chars: &mut Peekable<Chars>) -> String {
+    let mut word = String::new();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() || c == '(' || c == ')' || c == '"' {
+            break;
+        }
+        word.push(c);
+        chars.next();
+    }
+    word
+}
+
+// Reads up to the closing quote, the opening quote has already been read
+fn lex_quoted(chars: &mut Peekable<Chars>) -> Result<String, QueryError> {
+    let mut quoted = String::new();
+    for c in chars.by_ref() {
+        if c == '"' {
+            return Ok(quoted);
+        }
+        quoted.push(c);
+    }
+    Err(QueryError::UnterminatedQuote)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+    fn parse_or(&mut self) -> Result<Query, QueryError> {
+        let mut queries = vec![self.parse_and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            queries.push(self.parse_and()?);
+        }
+        Ok(flatten(queries, Query::Or))
+    }
+    fn parse_and(&mut self) -> Result<Query, QueryError> {
+        let mut queries = vec![self.parse_unary()?];
+        loop {
+            match self.peek() {
+                Some(Token::And) => {
+                    self.next();
+                }
+                Some(Token::Or) | Some(Token::RightParen) | None => break,
+                Some(_) => {}
+            }
+            queries.push(self.parse_unary()?);
+        }
+        Ok(flatten(queries, Query::And))
+    }
+    fn parse_unary(&mut self) -> Result<Query, QueryError> {
+        match self.next() {
+            Some(Token::Not) => Ok(Query::Not(Box::new(self.parse_unary()?))),
+            Some(Token::LeftParen) => {
+                let query = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RightParen) => Ok(query),
+                    Some(token) => Err(QueryError::UnexpectedToken(token.to_string())),
+                    None => Err(QueryError::UnexpectedEnd),
+                }
+            }
+            Some(Token::Word(word)) => Ok(Query::Filter(Filter::Text(word))),
+            Some(Token::Field(field, value)) => parse_filter(&field, &value).map(Query::Filter),
+            Some(token) => Err(QueryError::UnexpectedToken(token.to_string())),
+            None => Err(QueryError::UnexpectedEnd),
+        }
+    }
+}
+
+fn flatten(mut queries: Vec<Query>, join: fn(Vec<Query>) -> Query) -> Query {
+    if queries.len() == 1 {
+        queries.remove(0)
+    } else {
+        join(queries)
+    }
+}
+
+fn parse_filter(field: &str, value: &str) -> Result<Filter, QueryError> {
+    let invalid = || QueryError::InvalidValue {
+        field: field.to_string(),
+        value: value.to_string(),
+    };
+    if value.is_empty() {
+        return Err(invalid());
+    }
+    match field {
+        "tag" => Ok(Filter::Tag(value.to_string())),
+        "label" => Ok(Filter::Label(value.to_string())),
+        "text" => Ok(Filter::Body(value.to_string())),
+        "links" => value.parse().map(Filter::Links).map_err(|_| invalid()),
+        "id" => value.parse().map(Filter::Id).map_err(|_| invalid()),
+        "created" => {
+            let (comparison, date) = [
+                (">=", Comparison::GreaterOrEqual),
+                ("<=", Comparison::LessOrEqual),
+                (">", Comparison::Greater),
+                ("<", Comparison::Less),
+                ("=", Comparison::Equal),
+            ]
+            .into_iter()
+            .find_map(|(op, comparison)| Some((comparison, value.strip_prefix(op)?)))
+            .unwrap_or((Comparison::Equal, value));
+            parse_date(date)
+                .map(|date| Filter::Created(comparison, date))
+                .ok_or_else(invalid)
+        }
+        _ => Err(QueryError::UnknownField(field.to_string())),
+    }
+}
+
+fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .ok()
+        .or_else(|| {
+            date.parse::<DateTime<Utc>>()
+                .ok()
+                .map(|date| date.date_naive())
+        })
+}
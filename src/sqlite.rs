@@ -0,0 +1,234 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use rusqlite::{params, Connection, OpenFlags};
+
+use crate::{Note, NoteBackend, NoteBackendError, UnsavedNote};
+
+// Version of the database schema, stored in `PRAGMA user_version`
+const SCHEMA_VERSION: usize = 3;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS notes (
+        id          INTEGER PRIMARY KEY,
+        label       TEXT NOT NULL,
+        text        TEXT NOT NULL,
+        created_at  TEXT NOT NULL,
+        revision    INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE TABLE IF NOT EXISTS tags (
+        note_id     INTEGER NOT NULL REFERENCES notes(id) ON DELETE CASCADE,
+        position    INTEGER NOT NULL,
+        tag         TEXT NOT NULL,
+        PRIMARY KEY (note_id, position)
+    );
+    CREATE INDEX IF NOT EXISTS tags_by_tag ON tags(tag);
+    CREATE TABLE IF NOT EXISTS related_notes (
+        note_id     INTEGER NOT NULL REFERENCES notes(id) ON DELETE CASCADE,
+        position    INTEGER NOT NULL,
+        related_id  INTEGER NOT NULL,
+        PRIMARY KEY (note_id, position)
+    );
+    CREATE INDEX IF NOT EXISTS related_by_target ON related_notes(related_id);
+    CREATE TABLE IF NOT EXISTS counters (
+        name        TEXT PRIMARY KEY,
+        value       INTEGER NOT NULL
+    );
+    INSERT OR IGNORE INTO counters (name, value)
+        SELECT 'next_note_id', COALESCE(MAX(id), 0) + 1 FROM notes;
+";
+
+// Upgrades an existing database from version `i + 1` to `i + 2`
+const SCHEMA_UPGRADES: &[&str] = &[
+    "ALTER TABLE notes ADD COLUMN revision INTEGER NOT NULL DEFAULT 0;",
+    // The next note id counter is created and seeded by `SCHEMA`
+    "",
+];
+
+pub struct SqliteBackend {
+    connection: Connection,
+}
+
+impl SqliteBackend {
+    // Opens (or creates) the database at `file_path` and makes sure the schema exists
+    pub fn new(file_path: PathBuf) -> Result<Self, NoteBackendError> {
+        let connection = Connection::open(&file_path)?;
+        connection.pragma_update(None, "foreign_keys", true)?;
+
+        let version: usize =
+            connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version > SCHEMA_VERSION {
+            return Err(NoteBackendError::VersionMismatch {
+                found: version,
+                supported: SCHEMA_VERSION,
+            });
+        }
+        let tx = connection.unchecked_transaction()?;
+        if version > 0 {
+            for upgrade in &SCHEMA_UPGRADES[version - 1..] {
+                tx.execute_batch(upgrade)?;
+            }
+        }
+        tx.execute_batch(SCHEMA)?;
+        tx.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        tx.commit()?;
+
+        Ok(Self { connection })
+    }
+    // Opens an existing database without creating or upgrading anything, every write fails
+    pub fn open_read_only(file_path: PathBuf) -> Result<Self, NoteBackendError> {
+        let connection = Connection::open_with_flags(&file_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let version: usize =
+            connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version != SCHEMA_VERSION {
+            return Err(NoteBackendError::VersionMismatch {
+                found: version,
+                supported: SCHEMA_VERSION,
+            });
+        }
+        Ok(Self { connection })
+    }
+    // Explains why a write guarded by the revision of a note did not touch any row
+    fn missed_write_error(&self, id: usize) -> NoteBackendError {
+        let exists = self
+            .connection
+            .query_row("SELECT 1 FROM notes WHERE id = ?1", params![id], |_| Ok(()))
+            .is_ok();
+        if exists {
+            NoteBackendError::Conflict(id)
+        } else {
+            NoteBackendError::NotFound(id)
+        }
+    }
+}
+
+impl NoteBackend for SqliteBackend {
+    fn retrieve_notes(&self) -> Result<Vec<Note>, NoteBackendError> {
+        let mut tags: HashMap<usize, Vec<String>> = HashMap::new();
+        let mut stmt = self
+            .connection
+            .prepare("SELECT note_id, tag FROM tags ORDER BY note_id, position")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, usize>(0)?, row.get(1)?)))?;
+        for row in rows {
+            let (note_id, tag) = row?;
+            tags.entry(note_id).or_default().push(tag);
+        }
+
+        let mut related: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut stmt = self
+            .connection
+            .prepare("SELECT note_id, related_id FROM related_notes ORDER BY note_id, position")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, usize>(0)?, row.get(1)?)))?;
+        for row in rows {
+            let (note_id, related_id) = row?;
+            related.entry(note_id).or_default().push(related_id);
+        }
+
+        let mut stmt = self
+            .connection
+            .prepare("SELECT id, label, text, created_at, revision FROM notes ORDER BY id")?;
+        let notes = stmt
+            .query_map([], |row| {
+                let id: usize = row.get(0)?;
+                Ok(Note {
+                    id,
+                    label: row.get(1)?,
+                    text: row.get(2)?,
+                    created_at: row.get(3)?,
+                    tags: tags.remove(&id).unwrap_or_default(),
+                    related_notes: related.remove(&id).unwrap_or_default(),
+                    revision: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<Note>, _>>()?;
+        Ok(notes)
+    }
+    fn add_note(&self, note: UnsavedNote) -> Result<usize, NoteBackendError> {
+        let tx = self.connection.unchecked_transaction()?;
+        // The counter only grows, so ids of deleted notes are never reused
+        let new_id: usize = tx.query_row(
+            "SELECT MAX(value, (SELECT COALESCE(MAX(id), 0) + 1 FROM notes))
+             FROM counters WHERE name = 'next_note_id'",
+            [],
+            |row| row.get(0),
+        )?;
+        tx.execute(
+            "UPDATE counters SET value = ?1 WHERE name = 'next_note_id'",
+            params![new_id + 1],
+        )?;
+        let new_note = note.into_note(new_id);
+        tx.execute(
+            "INSERT INTO notes (id, label, text, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                new_note.id,
+                new_note.label,
+                new_note.text,
+                new_note.created_at
+            ],
+        )?;
+        insert_links(&tx, &new_note)?;
+        tx.commit()?;
+        Ok(new_id)
+    }
+    fn delete_note(&self, note: &Note) -> Result<(), NoteBackendError> {
+        let tx = self.connection.unchecked_transaction()?;
+        // Tags and links of the note are removed through ON DELETE CASCADE
+        let deleted = tx.execute(
+            "DELETE FROM notes WHERE id = ?1 AND revision = ?2",
+            params![note.id, note.revision],
+        )?;
+        if deleted == 0 {
+            return Err(self.missed_write_error(note.id));
+        }
+        tx.execute(
+            "UPDATE notes SET revision = revision + 1
+             WHERE id IN (SELECT note_id FROM related_notes WHERE related_id = ?1)",
+            params![note.id],
+        )?;
+        tx.execute(
+            "DELETE FROM related_notes WHERE related_id = ?1",
+            params![note.id],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+    fn update_note(&self, note: &Note) -> Result<(), NoteBackendError> {
+        let tx = self.connection.unchecked_transaction()?;
+        let updated = tx.execute(
+            "UPDATE notes SET label = ?3, text = ?4, created_at = ?5, revision = revision + 1
+             WHERE id = ?1 AND revision = ?2",
+            params![
+                note.id,
+                note.revision,
+                note.label,
+                note.text,
+                note.created_at
+            ],
+        )?;
+        if updated == 0 {
+            return Err(self.missed_write_error(note.id));
+        }
+        tx.execute("DELETE FROM tags WHERE note_id = ?1", params![note.id])?;
+        tx.execute(
+            "DELETE FROM related_notes WHERE note_id = ?1",
+            params![note.id],
+        )?;
+        insert_links(&tx, note)?;
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+// Writes the tag and related note rows of `note`, keeping their order through `position`
+fn insert_links(connection: &Connection, note: &Note) -> Result<(), NoteBackendError> {
+    let mut stmt =
+        connection.prepare("INSERT INTO tags (note_id, position, tag) VALUES (?1, ?2, ?3)")?;
+    for (position, tag) in note.tags.iter().enumerate() {
+        stmt.execute(params![note.id, position, tag])?;
+    }
+    let mut stmt = connection
+        .prepare("INSERT INTO related_notes (note_id, position, related_id) VALUES (?1, ?2, ?3)")?;
+    for (position, related_id) in note.related_notes.iter().enumerate() {
+        stmt.execute(params![note.id, position, related_id])?;
+    }
+    Ok(())
+}
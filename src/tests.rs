@@ -1,7 +1,32 @@
-use std::path::PathBuf;
+use std::{fs, path::PathBuf};
+
+use tempfile::TempDir;
 
 use super::*;
 
+// Copies the test data into a temporary directory, so tests that write can run in parallel
+fn temp_test_json() -> (TempDir, PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("test.json");
+    fs::copy(
+        ["test_data", "test.json"].iter().collect::<PathBuf>(),
+        &path,
+    )
+    .unwrap();
+    (dir, path)
+}
+
+fn note(id: usize, label: &str, text: &str, tags: &[&str]) -> Note {
+    UnsavedNote::new(
+        label.to_string(),
+        text.to_string(),
+        tags.iter().map(|tag| tag.to_string()).collect(),
+        Vec::new(),
+        Utc::now(),
+    )
+    .into_note(id)
+}
+
 #[test]
 fn test_load_json_notes() {
     let path: PathBuf = ["test_data", "test.json"].iter().collect();
@@ -17,14 +42,14 @@ fn test_load_json_notes() {
             .unwrap(),
         tags: vec![String::from("npc"), String::from("neverwinter")],
         related_notes: vec![1, 2, 3],
+        revision: 0,
     }];
     assert_eq!(notes, expected)
 }
 
 #[test]
 fn test_add_note_to_json() {
-    let path: PathBuf = ["test_data", "test.json"].iter().collect();
-    println!("{:?}", path);
+    let (_dir, path) = temp_test_json();
     let jb = JsonBackend::new(path);
 
     // Test retrieving test.json
@@ -38,6 +63,7 @@ fn test_add_note_to_json() {
             .unwrap(),
         tags: vec![String::from("npc"), String::from("neverwinter")],
         related_notes: vec![1, 2, 3],
+        revision: 0,
     }];
     assert_eq!(notes, expected_original);
 
@@ -62,6 +88,7 @@ fn test_add_note_to_json() {
                 .unwrap(),
             tags: vec![String::from("npc"), String::from("neverwinter")],
             related_notes: vec![1, 2, 3],
+            revision: 0,
         },
         Note {
             id: 1,
@@ -72,13 +99,760 @@ fn test_add_note_to_json() {
                 .unwrap(),
             tags: vec![String::from("npc"), String::from("neverwinter")],
             related_notes: vec![0, 2, 3],
+            revision: 0,
         },
     ];
     let notes = jb.retrieve_notes().unwrap();
     assert_eq!(notes, expected_added);
 
-    // Test deleting a note
-    jb.delete_note(1).unwrap();
+    // Test deleting a note, which also removes the link to it from the first note
+    jb.delete_note(&expected_added[1]).unwrap();
     let notes = jb.retrieve_notes().unwrap();
-    assert_eq!(notes, expected_original);
+    let mut expected_deleted = expected_original;
+    expected_deleted[0].related_notes = vec![2, 3];
+    expected_deleted[0].revision = 1;
+    assert_eq!(notes, expected_deleted);
+}
+
+#[test]
+fn test_sqlite_backend() {
+    let dir = tempfile::tempdir().unwrap();
+    let sb = SqliteBackend::new(dir.path().join("notes.db")).unwrap();
+    assert_eq!(sb.retrieve_notes().unwrap(), Vec::new());
+
+    let created_at = "2012-01-01T00:00:00Z"
+        .parse::<chrono::DateTime<Utc>>()
+        .unwrap();
+    sb.add_note(UnsavedNote::new(
+        "Testing title".to_string(),
+        "a very long string".to_string(),
+        vec!["npc".to_string(), "neverwinter".to_string()],
+        vec![3, 2],
+        created_at,
+    ))
+    .unwrap();
+    sb.add_note(UnsavedNote::new(
+        "Testing title 1".to_string(),
+        "a very long string 1".to_string(),
+        vec!["location".to_string()],
+        vec![1],
+        created_at,
+    ))
+    .unwrap();
+
+    let mut notes = sb.retrieve_notes().unwrap();
+    assert_eq!(notes.len(), 2);
+    assert_eq!(notes[0].id, 1);
+    assert_eq!(notes[0].tags, vec!["npc", "neverwinter"]);
+    assert_eq!(notes[0].related_notes, vec![3, 2]);
+    assert_eq!(notes[0].created_at, created_at);
+    assert_eq!(notes[1].id, 2);
+
+    // Updating replaces the tags and links of the note
+    notes[0].text = "a short string".to_string();
+    notes[0].tags = vec!["dead".to_string()];
+    notes[0].related_notes = vec![2];
+    sb.update_note(&notes[0]).unwrap();
+    notes[0].revision += 1;
+    assert_eq!(sb.retrieve_notes().unwrap()[0], notes[0]);
+
+    // Deleting removes the note together with its tags and links, and the links to it
+    sb.delete_note(&notes[0]).unwrap();
+    notes[1].related_notes = Vec::new();
+    notes[1].revision += 1;
+    assert_eq!(sb.retrieve_notes().unwrap(), vec![notes[1].clone()]);
+    assert!(matches!(
+        sb.update_note(&notes[0]),
+        Err(NoteBackendError::NotFound(1))
+    ));
+}
+
+#[test]
+fn test_markdown_dir_backend() {
+    let dir = tempfile::tempdir().unwrap();
+    let mb = MarkdownDirBackend::new(dir.path().join("notes"));
+    assert_eq!(mb.retrieve_notes().unwrap(), Vec::new());
+
+    let created_at = "2012-01-01T00:00:00Z"
+        .parse::<chrono::DateTime<Utc>>()
+        .unwrap();
+    mb.add_note(UnsavedNote::new(
+        "Testing title".to_string(),
+        "a very long string\n+++\nwith a delimiter in it\n".to_string(),
+        vec!["npc".to_string(), "neverwinter".to_string()],
+        vec![2, 3],
+        created_at,
+    ))
+    .unwrap();
+    let mut notes = mb.retrieve_notes().unwrap();
+    assert_eq!(
+        notes,
+        vec![Note {
+            id: 1,
+            label: "Testing title".to_string(),
+            text: "a very long string\n+++\nwith a delimiter in it\n".to_string(),
+            created_at,
+            tags: vec![String::from("npc"), String::from("neverwinter")],
+            related_notes: vec![2, 3],
+            revision: 0,
+        }]
+    );
+
+    // Notes written by hand are picked up, whatever the file is called
+    fs::write(
+        dir.path().join("notes").join("tavern.md"),
+        "+++\nid = 7\nlabel = \"Yawning Portal\"\ncreated_at = \"2012-01-01T00:00:00Z\"\n+++",
+    )
+    .unwrap();
+    let hand_written = mb.retrieve_notes().unwrap().pop().unwrap();
+    assert_eq!(hand_written.id, 7);
+    assert_eq!(hand_written.label, "Yawning Portal");
+    assert_eq!(hand_written.text, "");
+
+    notes[0].text = String::new();
+    mb.update_note(&notes[0]).unwrap();
+    notes[0].revision += 1;
+    assert_eq!(mb.retrieve_notes().unwrap()[0], notes[0]);
+
+    mb.delete_note(&hand_written).unwrap();
+    assert_eq!(mb.retrieve_notes().unwrap(), notes);
+
+    // Other markdown files are skipped, and notes saved with CRLF line endings are read
+    let notes_dir = dir.path().join("notes");
+    fs::write(notes_dir.join("README.md"), "# My notes\n").unwrap();
+    fs::write(
+        notes_dir.join("windows.md"),
+        "+++\r\nid = 9\r\nlabel = \"Baldur's Gate\"\r\ncreated_at = \"2012-01-01T00:00:00Z\"\r\n+++\r\nA city\r\n",
+    )
+    .unwrap();
+    let stored = mb.retrieve_notes().unwrap();
+    assert_eq!(stored.len(), 2);
+    assert_eq!(stored[1].label, "Baldur's Gate");
+    assert_eq!(stored[1].text, "A city\r\n");
+    mb.update_note(&stored[1]).unwrap();
+
+    // A file taken by the next id is left alone and the id after it is used
+    fs::write(notes_dir.join("10.md"), "# Session 10\n").unwrap();
+    let id = mb
+        .add_note(UnsavedNote::new(
+            "Luskan".to_string(),
+            String::new(),
+            Vec::new(),
+            Vec::new(),
+            created_at,
+        ))
+        .unwrap();
+    assert_eq!(id, 11);
+    assert_eq!(
+        fs::read_to_string(notes_dir.join("10.md")).unwrap(),
+        "# Session 10\n"
+    );
+    assert_eq!(mb.retrieve_notes().unwrap().len(), 3);
+}
+
+fn add_summary(mut blob: serde_json::Value) -> Result<serde_json::Value, NoteBackendError> {
+    for note in blob["notes"].as_array_mut().unwrap() {
+        note["summary"] = serde_json::Value::from("");
+    }
+    Ok(blob)
+}
+
+fn rename_summary(mut blob: serde_json::Value) -> Result<serde_json::Value, NoteBackendError> {
+    for note in blob["notes"].as_array_mut().unwrap() {
+        let summary = note.as_object_mut().unwrap().remove("summary").unwrap();
+        note["abstract"] = summary;
+    }
+    Ok(blob)
+}
+
+#[test]
+fn test_migrate_json() {
+    let (_dir, path) = temp_test_json();
+    let original = fs::read_to_string(&path).unwrap();
+    let jb = JsonBackend::new(path.clone());
+
+    // Nothing to do when the file already has the current version
+    let report = jb.migrate().unwrap();
+    assert!(report.is_empty());
+    assert_eq!(report.backup_path, None);
+
+    let migrations = [
+        Migration {
+            from_version: 3,
+            description: "rename summary to abstract",
+            migrate: rename_summary,
+        },
+        Migration {
+            from_version: 2,
+            description: "add summary",
+            migrate: add_summary,
+        },
+    ];
+    let report = jb.migrate_with(&migrations, 4).unwrap();
+    assert_eq!(report.from_version, 2);
+    assert_eq!(report.to_version, 4);
+    assert_eq!(
+        report.applied,
+        vec!["add summary", "rename summary to abstract"]
+    );
+    let backup_path = report.backup_path.unwrap();
+    assert_eq!(fs::read_to_string(backup_path).unwrap(), original);
+
+    let migrated: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(migrated["version"], 4);
+    assert_eq!(migrated["notes"][0]["abstract"], "");
+    assert!(migrated["notes"][0].get("summary").is_none());
+
+    // A missing step is reported instead of leaving a half migrated file
+    assert!(matches!(
+        jb.migrate_with(&migrations, 6),
+        Err(NoteBackendError::MissingMigration(4))
+    ));
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        serde_json::to_string_pretty(&migrated).unwrap()
+    );
+}
+
+#[test]
+fn test_newer_json_version() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.json");
+    fs::write(&path, r#"{"version": 99, "notes": []}"#).unwrap();
+    let jb = JsonBackend::new(path);
+    assert!(matches!(
+        jb.retrieve_notes(),
+        Err(NoteBackendError::VersionMismatch {
+            found: 99,
+            supported: BACKEND_VERSION
+        })
+    ));
+    assert!(matches!(
+        jb.migrate(),
+        Err(NoteBackendError::VersionMismatch { found: 99, .. })
+    ));
+}
+
+#[test]
+fn test_json_backend_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.json");
+    let jb = JsonBackend::new(path.clone());
+    assert!(matches!(
+        jb.retrieve_notes(),
+        Err(NoteBackendError::MissingFile(missing)) if missing == path
+    ));
+
+    let note = note(4, "", "", &[]);
+    fs::write(&path, r#"{"version": 2, "next_id": 1, "notes": {}}"#).unwrap();
+    assert!(matches!(
+        jb.delete_note(&note),
+        Err(NoteBackendError::CorruptData(_))
+    ));
+
+    fs::write(&path, r#"{"version": 2, "next_id": 1, "notes": []}"#).unwrap();
+    assert!(matches!(
+        jb.update_note(&note),
+        Err(NoteBackendError::NotFound(4))
+    ));
+}
+
+#[test]
+fn test_json_concurrent_writes() {
+    let (dir, path) = temp_test_json();
+    let handles: Vec<_> = (0..8)
+        .map(|i| {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                // Every thread has its own backend, like separate application instances
+                let jb = JsonBackend::new(path);
+                for j in 0..5 {
+                    let note = UnsavedNote::new(
+                        format!("Note {} {}", i, j),
+                        String::new(),
+                        Vec::new(),
+                        Vec::new(),
+                        Utc::now(),
+                    );
+                    jb.add_note(note).unwrap();
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let notes = JsonBackend::new(path).retrieve_notes().unwrap();
+    assert_eq!(notes.len(), 41);
+    let ids: std::collections::HashSet<usize> = notes.iter().map(|note| note.id).collect();
+    assert_eq!(ids.len(), 41);
+
+    // Only the data file and its lock file are left, no temporary files
+    let mut file_names: Vec<String> = fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    file_names.sort();
+    assert_eq!(file_names, vec!["test.json", "test.json.lock"]);
+}
+
+// Runs the optimistic concurrency checks against a backend that stores a single note
+fn check_conflicts<B: NoteBackend>(backend: &B) {
+    let mut mine = backend.retrieve_notes().unwrap().pop().unwrap();
+    let mut theirs = mine.clone();
+
+    theirs.text = "their text".to_string();
+    backend.update_note(&theirs).unwrap();
+
+    // A stale note can neither be written back nor deleted
+    mine.text = "my text".to_string();
+    assert!(matches!(
+        backend.update_note(&mine),
+        Err(NoteBackendError::Conflict(id)) if id == mine.id
+    ));
+    assert!(matches!(
+        backend.delete_note(&mine),
+        Err(NoteBackendError::Conflict(_))
+    ));
+    let stored = backend.retrieve_notes().unwrap().pop().unwrap();
+    assert_eq!(stored.text, "their text");
+    assert_eq!(stored.revision, mine.revision + 1);
+
+    // Writing on top of the current revision succeeds
+    mine.revision = stored.revision;
+    backend.update_note(&mine).unwrap();
+    let stored = backend.retrieve_notes().unwrap().pop().unwrap();
+    assert_eq!(stored.text, "my text");
+    backend.delete_note(&stored).unwrap();
+    assert!(matches!(
+        backend.delete_note(&stored),
+        Err(NoteBackendError::NotFound(_))
+    ));
+}
+
+#[test]
+fn test_update_conflicts() {
+    let (_dir, path) = temp_test_json();
+    check_conflicts(&JsonBackend::new(path));
+
+    let dir = tempfile::tempdir().unwrap();
+    let note = UnsavedNote::new(
+        "Testing title".to_string(),
+        String::new(),
+        Vec::new(),
+        Vec::new(),
+        Utc::now(),
+    );
+    let sb = SqliteBackend::new(dir.path().join("notes.db")).unwrap();
+    sb.add_note(note.clone()).unwrap();
+    check_conflicts(&sb);
+
+    let mb = MarkdownDirBackend::new(dir.path().join("notes"));
+    mb.add_note(note).unwrap();
+    check_conflicts(&mb);
+}
+
+#[test]
+fn test_sqlite_schema_upgrade() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.db");
+    {
+        // The first schema, without note revisions
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE notes (id INTEGER PRIMARY KEY, label TEXT NOT NULL,
+                     text TEXT NOT NULL, created_at TEXT NOT NULL);
+                 INSERT INTO notes VALUES (1, 'Testing title', '', '2012-01-01T00:00:00Z');
+                 PRAGMA user_version = 1;",
+            )
+            .unwrap();
+    }
+    // Opening read-only leaves the old schema alone
+    assert!(matches!(
+        SqliteBackend::open_read_only(path.clone()),
+        Err(NoteBackendError::VersionMismatch { found: 1, .. })
+    ));
+    let sb = SqliteBackend::new(path.clone()).unwrap();
+    let note = sb.retrieve_notes().unwrap().pop().unwrap();
+    assert_eq!(note.label, "Testing title");
+    assert_eq!(note.revision, 0);
+    sb.update_note(&note).unwrap();
+
+    let read_only = SqliteBackend::open_read_only(path).unwrap();
+    assert_eq!(read_only.retrieve_notes().unwrap().len(), 1);
+    assert!(read_only.update_note(&note).is_err());
+}
+
+#[test]
+fn test_json_retrieve_unlocked() {
+    let (_dir, path) = temp_test_json();
+    let jb = JsonBackend::new(path.clone());
+    let notes = jb.retrieve_notes_unlocked().unwrap();
+    assert!(!path.with_file_name("test.json.lock").exists());
+    assert_eq!(notes, jb.retrieve_notes().unwrap());
+}
+
+#[test]
+fn test_migrate_json_next_id() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.json");
+    let mut blob: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(["test_data", "test.json"].iter().collect::<PathBuf>()).unwrap(),
+    )
+    .unwrap();
+    blob["version"] = serde_json::Value::from(1);
+    blob.as_object_mut().unwrap().remove("next_id");
+    blob["notes"][0]["id"] = serde_json::Value::from(4);
+    fs::write(&path, blob.to_string()).unwrap();
+
+    let jb = JsonBackend::new(path);
+    let report = jb.migrate().unwrap();
+    assert_eq!(report.from_version, 1);
+    assert_eq!(report.to_version, BACKEND_VERSION);
+    assert_eq!(report.applied.len(), 1);
+    assert!(report.backup_path.unwrap().exists());
+
+    let note = UnsavedNote::new(
+        String::new(),
+        String::new(),
+        Vec::new(),
+        Vec::new(),
+        Utc::now(),
+    );
+    jb.add_note(note).unwrap();
+    let ids: Vec<usize> = jb.retrieve_notes().unwrap().iter().map(|n| n.id).collect();
+    assert_eq!(ids, vec![4, 5]);
+}
+
+// Adds two notes, deletes the newest one and checks that its id is not handed out again
+fn check_ids_not_reused<B: NoteBackend>(backend: &B) {
+    let note = UnsavedNote::new(
+        "Testing title".to_string(),
+        String::new(),
+        Vec::new(),
+        Vec::new(),
+        Utc::now(),
+    );
+    backend.add_note(note.clone()).unwrap();
+    let newest_id = backend.add_note(note.clone()).unwrap();
+    let newest = backend.retrieve_notes().unwrap().pop().unwrap();
+    // `add_note` returns the id the note was stored with
+    assert_eq!(newest.id, newest_id);
+    backend.delete_note(&newest).unwrap();
+
+    assert_eq!(backend.add_note(note).unwrap(), newest.id + 1);
+    let added = backend.retrieve_notes().unwrap().pop().unwrap();
+    assert_eq!(added.id, newest.id + 1);
+}
+
+#[test]
+fn test_ids_not_reused() {
+    let (dir, path) = temp_test_json();
+    check_ids_not_reused(&JsonBackend::new(path));
+    check_ids_not_reused(&SqliteBackend::new(dir.path().join("notes.db")).unwrap());
+    check_ids_not_reused(&MarkdownDirBackend::new(dir.path().join("notes")));
+}
+
+// Deletes a note that another note links to, and checks that the link is removed with it
+fn check_delete_removes_links<B: NoteBackend>(backend: &B) {
+    let note = |related_notes: Vec<usize>| {
+        UnsavedNote::new(
+            "Testing title".to_string(),
+            String::new(),
+            Vec::new(),
+            related_notes,
+            Utc::now(),
+        )
+    };
+    backend.add_note(note(Vec::new())).unwrap();
+    let target = backend.retrieve_notes().unwrap().pop().unwrap();
+    backend.add_note(note(vec![target.id, 999])).unwrap();
+    let linking = backend.retrieve_notes().unwrap().pop().unwrap();
+
+    backend.delete_note(&target).unwrap();
+    let stored = backend.retrieve_notes().unwrap().pop().unwrap();
+    assert_eq!(stored.related_notes, vec![999]);
+    // The linking note changed, so a stale copy of it conflicts
+    assert!(matches!(
+        backend.update_note(&linking),
+        Err(NoteBackendError::Conflict(_))
+    ));
+}
+
+#[test]
+fn test_delete_removes_links() {
+    let (dir, path) = temp_test_json();
+    check_delete_removes_links(&JsonBackend::new(path));
+    check_delete_removes_links(&SqliteBackend::new(dir.path().join("notes.db")).unwrap());
+    check_delete_removes_links(&MarkdownDirBackend::new(dir.path().join("notes")));
+}
+
+#[test]
+fn test_repair_dangling_links() {
+    let (_dir, path) = temp_test_json();
+    let jb = JsonBackend::new(path);
+    // The test note links to the notes 1, 2 and 3, of which only 1 is added
+    let note = UnsavedNote::new(
+        "Testing title 1".to_string(),
+        String::new(),
+        Vec::new(),
+        vec![0],
+        Utc::now(),
+    );
+    jb.add_note(note).unwrap();
+
+    let notes = jb.retrieve_notes().unwrap();
+    let dangling = vec![
+        DanglingLink {
+            note_id: 0,
+            missing_id: 2,
+        },
+        DanglingLink {
+            note_id: 0,
+            missing_id: 3,
+        },
+    ];
+    assert_eq!(find_dangling_links(&notes), dangling);
+
+    assert_eq!(repair_dangling_links(&jb).unwrap(), dangling);
+    let notes = jb.retrieve_notes().unwrap();
+    assert_eq!(notes[0].related_notes, vec![1]);
+    assert_eq!(notes[1].related_notes, vec![0]);
+    assert_eq!(find_dangling_links(&notes), Vec::new());
+}
+
+#[test]
+fn test_backlinks() {
+    let notes = vec![
+        Note {
+            related_notes: vec![2, 3],
+            ..note(1, "Note 1", "", &[])
+        },
+        Note {
+            related_notes: vec![2],
+            ..note(2, "Note 2", "", &[])
+        },
+        Note {
+            related_notes: vec![1, 2],
+            ..note(3, "Note 3", "", &[])
+        },
+    ];
+    let linked_from: Vec<usize> = backlinks(&notes, 2).iter().map(|n| n.id).collect();
+    assert_eq!(linked_from, vec![1, 3]);
+    let linked_from: Vec<usize> = backlinks(&notes, 1).iter().map(|n| n.id).collect();
+    assert_eq!(linked_from, vec![3]);
+    assert!(backlinks(&notes, 4).is_empty());
+}
+
+#[test]
+fn test_parse_wiki_links() {
+    let text = "See [[Neverwinter]] and [[ #3 ]], not [[]] or [[broken\nlink]].\n\
+                [[Neverwinter]] again, [[a [[Nested]] and [[#x]] [[unclosed";
+    assert_eq!(
+        parse_wiki_links(text),
+        vec![
+            WikiLink::Label("Neverwinter".to_string()),
+            WikiLink::Id(3),
+            WikiLink::Label("Nested".to_string()),
+            WikiLink::Label("#x".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_unsaved_note_from_text() {
+    let from_text = |label: Option<&str>, text: &str| {
+        UnsavedNote::from_text(
+            label.map(str::to_string),
+            text.to_string(),
+            Vec::new(),
+            Utc::now(),
+        )
+        .map(|note| note.label)
+    };
+    assert_eq!(
+        from_text(None, "\ncommit 1a2b\nAuthor: Drizzt\n").as_deref(),
+        Some("commit 1a2b")
+    );
+    assert_eq!(
+        from_text(None, "Some intro\n\n#tag\n## Neverwinter ##\n").as_deref(),
+        Some("Neverwinter ##")
+    );
+    // Empty headings are skipped
+    assert_eq!(from_text(None, "#\n# \nText").as_deref(), Some("Text"));
+    assert_eq!(
+        from_text(Some("Waterdeep"), "# Luskan").as_deref(),
+        Some("Waterdeep")
+    );
+    assert_eq!(from_text(Some(" "), " \n\t\n"), None);
+    assert_eq!(
+        from_text(None, &"a".repeat(100)).map(|label| label.len()),
+        Some(80)
+    );
+}
+
+#[test]
+fn test_sync_wiki_links() {
+    let notes = vec![
+        note(1, "Neverwinter", "", &[]),
+        note(2, "Waterdeep", "", &[]),
+        note(3, "Baldur's Gate", "", &[]),
+        Note {
+            related_notes: vec![2, 3],
+            ..note(4, "Journal", "Went to [[Waterdeep]]", &[])
+        },
+    ];
+    let mut journal = notes[3].clone();
+    journal.text = "Went to [[neverwinter]], [[#4]] and [[Luskan]], then [[#9]]".to_string();
+    let unresolved = sync_wiki_links(&mut journal, &notes[3].text, &notes);
+    // Waterdeep is no longer written, the manual link to 3 is kept and self links are ignored
+    assert_eq!(journal.related_notes, vec![3, 1]);
+    assert_eq!(
+        unresolved,
+        vec![WikiLink::Label("Luskan".to_string()), WikiLink::Id(9)]
+    );
+}
+
+#[test]
+fn test_search_index() {
+    let mut notes = vec![
+        note(
+            1,
+            "Waterdeep",
+            "The city of splendors, north of Baldur's Gate",
+            &["city"],
+        ),
+        note(2, "Baldur's Gate", "A city on the Sword Coast", &["city"]),
+        note(
+            3,
+            "Journal",
+            "Travelled from Waterdeep to Baldur's Gate",
+            &[],
+        ),
+        note(4, "Drizzt", "A drow ranger", &["npc"]),
+    ];
+    let mut index = SearchIndex::new(&notes);
+    assert_eq!(index.len(), 4);
+    let ids = |results: Vec<(usize, f64)>| results.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+
+    // A match in the label ranks above the same match in the text, shorter notes rank higher
+    assert_eq!(ids(index.search("baldur")), vec![2, 3, 1]);
+    assert_eq!(ids(index.search("waterdeep")), vec![1, 3]);
+    // Every term has to match, the last ones can be the start of a word
+    assert_eq!(ids(index.search("city sword")), vec![2]);
+    assert_eq!(ids(index.search("Travel wat")), vec![3]);
+    assert!(index.search("dragon").is_empty());
+    assert!(index.search("").is_empty());
+
+    // Updating the index gives the same results as building it from scratch
+    notes[3].text = "A drow ranger from the city of Menzoberranzan".to_string();
+    index.insert(&notes[3]);
+    index.remove(notes[0].id);
+    notes.remove(0);
+    let rebuilt = SearchIndex::new(&notes);
+    for query in ["city", "baldur", "drow", "waterdeep", "splendors"] {
+        assert_eq!(index.search(query), rebuilt.search(query));
+    }
+    assert_eq!(ids(index.search("city")), vec![2, 4]);
+}
+
+#[test]
+fn test_parse_query() {
+    let query = Query::parse(
+        r#"tag:npc AND NOT tag:dead label:"Neverwinter Wood" created:>2024-01-01 links:12"#,
+    )
+    .unwrap();
+    let date = chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+    assert_eq!(
+        query,
+        Query::And(vec![
+            Query::Filter(Filter::Tag("npc".to_string())),
+            Query::Not(Box::new(Query::Filter(Filter::Tag("dead".to_string())))),
+            Query::Filter(Filter::Label("Neverwinter Wood".to_string())),
+            Query::Filter(Filter::Created(Comparison::Greater, date)),
+            Query::Filter(Filter::Links(12)),
+        ])
+    );
+
+    // AND binds tighter than OR, parentheses group
+    let query = Query::parse("a b OR -(c OR id:3)").unwrap();
+    let text = |text: &str| Query::Filter(Filter::Text(text.to_string()));
+    assert_eq!(
+        query,
+        Query::Or(vec![
+            Query::And(vec![text("a"), text("b")]),
+            Query::Not(Box::new(Query::Or(vec![
+                text("c"),
+                Query::Filter(Filter::Id(3))
+            ]))),
+        ])
+    );
+    assert!(Query::parse("some plain words").unwrap().is_plain_text());
+    assert!(!query.is_plain_text());
+
+    assert_eq!(Query::parse(""), Err(QueryError::UnexpectedEnd));
+    assert_eq!(Query::parse("a OR"), Err(QueryError::UnexpectedEnd));
+    assert_eq!(Query::parse("(a b"), Err(QueryError::UnexpectedEnd));
+    assert_eq!(
+        Query::parse("a )"),
+        Err(QueryError::UnexpectedToken(")".to_string()))
+    );
+    assert_eq!(
+        Query::parse(r#"label:"open"#),
+        Err(QueryError::UnterminatedQuote)
+    );
+    assert_eq!(
+        Query::parse("colour:red"),
+        Err(QueryError::UnknownField("colour".to_string()))
+    );
+    assert_eq!(
+        Query::parse("created:>yesterday"),
+        Err(QueryError::InvalidValue {
+            field: "created".to_string(),
+            value: ">yesterday".to_string()
+        })
+    );
+}
+
+#[test]
+fn test_evaluate_query() {
+    let notes = vec![
+        Note {
+            created_at: "2023-06-01T12:00:00Z".parse().unwrap(),
+            related_notes: vec![3],
+            ..note(1, "Drizzt", "The text of Drizzt", &["npc"])
+        },
+        Note {
+            created_at: "2024-03-01T12:00:00Z".parse().unwrap(),
+            ..note(2, "Old Hermit", "The text of Old Hermit", &["npc", "dead"])
+        },
+        Note {
+            created_at: "2024-01-01T08:00:00Z".parse().unwrap(),
+            related_notes: vec![1],
+            ..note(3, "Neverwinter", "The text of Neverwinter", &["city"])
+        },
+        Note {
+            created_at: "2024-05-05T12:00:00Z".parse().unwrap(),
+            related_notes: vec![3],
+            ..note(4, "Gundren", "The text of Gundren", &["NPC"])
+        },
+    ];
+    let ids = |query: &str| {
+        Query::parse(query)
+            .unwrap()
+            .filter(&notes)
+            .iter()
+            .map(|note| note.id)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(ids("tag:npc AND NOT tag:dead"), vec![1, 4]);
+    assert_eq!(ids("tag:npc created:>=2024-01-01"), vec![2, 4]);
+    assert_eq!(ids("created:2024-01-01"), vec![3]);
+    assert_eq!(ids("created:<2024-01-01 OR tag:city"), vec![1, 3]);
+    assert_eq!(ids("links:3 -label:drizzt"), vec![4]);
+    assert_eq!(ids(r#""text of n""#), vec![3]);
+    assert_eq!(ids("hermit OR id:1"), vec![1, 2]);
 }